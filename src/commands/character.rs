@@ -1,8 +1,10 @@
-use crate::utils::common::*;
-use crate::{errln, infoln, warnln};
+use crate::utils::{common::*, error::ClrpgError, print::*, classes::*, items::{Inventory, ItemEffect}, ledger::*};
+use crate::{debugln, errln, infoln, warnln};
 
 use clap::{Args, Subcommand};
 use colored::Colorize;
+use rand::Rng;
+use serde::Serialize;
 use tabled::object::{Columns, Object, Rows};
 use tabled::{Disable, Margin, Modify, Style, Table};
 
@@ -11,6 +13,38 @@ pub struct New {
     /// New character name.
     #[clap(short, long, value_parser)]
     name: Option<String>,
+
+    /// Character class: warrior, mage, cleric, rogue or a custom one.
+    #[clap(short, long, value_parser)]
+    class: Option<String>,
+
+    /// Spread a budget of points across the stats instead of rolling them;
+    /// stats not given as flags are asked for.
+    #[clap(long, action, conflicts_with = "reroll")]
+    point_buy: bool,
+
+    #[clap(long, value_parser, requires = "point-buy")]
+    power: Option<i32>,
+
+    #[clap(long, value_parser, requires = "point-buy")]
+    block: Option<i32>,
+
+    #[clap(long, value_parser, requires = "point-buy")]
+    magic: Option<i32>,
+
+    #[clap(long, value_parser, requires = "point-buy")]
+    faith: Option<i32>,
+
+    #[clap(long, value_parser, requires = "point-buy")]
+    speed: Option<i32>,
+
+    /// Roll N stat sets and pick one of them.
+    #[clap(long, value_parser, value_name = "N")]
+    reroll: Option<u32>,
+
+    /// Set to pick when rerolling, counting from 1; asked for if not given.
+    #[clap(long, value_parser, value_name = "SET", requires = "reroll")]
+    pick: Option<u32>,
 }
 
 #[derive(Args)]
@@ -27,6 +61,53 @@ pub struct Wait {
     name: String,
 }
 
+#[derive(Args)]
+pub struct Show {
+    /// Character name.
+    #[clap(value_parser)]
+    name: String,
+}
+
+#[derive(Args)]
+pub struct Delete {
+    /// Character name.
+    #[clap(value_parser)]
+    name: String,
+}
+
+#[derive(Args)]
+pub struct Retire {
+    /// Character name.
+    #[clap(value_parser)]
+    name: String,
+}
+
+#[derive(Args)]
+pub struct Revive {
+    /// Name of the fallen character.
+    #[clap(value_parser)]
+    name: String,
+
+    /// Character paying for the rites; the fallen pay out of their own purse if not given.
+    #[clap(short, long, value_parser)]
+    payer: Option<String>,
+
+    /// Item offered to the temple instead of gold, like a Phoenix Feather.
+    #[clap(short, long, value_parser)]
+    item: Option<String>,
+}
+
+#[derive(Args)]
+pub struct Rename {
+    /// Current character name.
+    #[clap(value_parser)]
+    name: String,
+
+    /// New character name.
+    #[clap(value_parser)]
+    new_name: String,
+}
+
 #[derive(Subcommand)]
 enum Subcommands {
     /// Create new character.
@@ -38,6 +119,27 @@ enum Subcommands {
     /// Wait a character
     Wait(Wait),
 
+    /// Show the full character sheet.
+    Show(Show),
+
+    /// Delete a character for good.
+    Delete(Delete),
+
+    /// Retire a character to the hall of fame.
+    Retire(Retire),
+
+    /// Rename a character.
+    Rename(Rename),
+
+    /// List the retired characters in the hall of fame.
+    Hall,
+
+    /// Pay the temple to bring a fallen character back from the graveyard.
+    Revive(Revive),
+
+    /// List the classes available to new characters.
+    Classes,
+
 }
 
 #[derive(Args)]
@@ -46,10 +148,125 @@ pub struct Character {
     command: Subcommands,
 }
 
-fn create_new(new: &New) -> Result<String, String> {
+/// Rolls the starting stats, or lets the player buy or pick them. Rerolled
+/// sets come from the dungeon seed and the id of the character.
+fn choose_stats(
+    new: &New, class: Option<&CharacterClass>, seed: u64, id: &str, state: &mut RandomState
+) -> Result<(CharacterStats, Creation), ClrpgError> {
+    if new.point_buy {
+        let health = class.map_or(BASE_HEALTH, |c| c.health());
+        let [power, block, magic, faith, speed] = buy_points(new)?;
+        return Ok((
+            CharacterStats::new(health, power, block, magic, faith, speed),
+            Creation::PointBuy { power, block, magic, faith, speed }
+        ));
+    }
+
+    let roll = |state: &mut RandomState| match class {
+        Some(class) => class.roll_stats(state),
+        None => CharacterStats::from_rng(state),
+    };
+
+    let rolls = match new.reroll {
+        None => return Ok((roll(state), Creation::Random)),
+        Some(rolls) => rolls,
+    };
+
+    let mut sets = state.with_seed(Creation::reroll_seed(seed, id), |state| {
+        (0..rolls).map(|_| roll(state)).collect::<Vec<CharacterStats>>()
+    });
+
+    let picked = match new.pick {
+        Some(picked) => picked,
+        None => {
+            print_choices(&sets.iter().map(|set| format!(
+                "POWER: {:<3} BLOCK: {:<3} MAGIC: {:<3} FAITH: {:<3} SPEED: {:<3} {}",
+                set.power(), set.block(), set.magic(), set.faith(), set.speed(),
+                format!("(total {})", set.total()).black()
+            )).collect::<Vec<String>>());
+            loop {
+                let answer = prompt(&format!("Pick a set [1-{}]:", rolls)).ok_or_else(|| {
+                    ClrpgError::InvalidChoice("No set was picked.".to_string())
+                })?;
+                match answer.parse::<u32>() {
+                    Ok(picked) if (1..=rolls).contains(&picked) => break picked,
+                    _ => errln!("{} is not one of the sets", answer),
+                }
+            }
+        },
+    };
+    Ok((sets.swap_remove(picked as usize - 1), Creation::Reroll { rolls, picked }))
+}
+
+/// Collects the points spent on each stat from the flags, asking for the
+/// missing ones. Every answer leaves enough points for the stats still to
+/// come.
+fn buy_points(new: &New) -> Result<[i32; 5], ClrpgError> {
+    let given = [new.power, new.block, new.magic, new.faith, new.speed];
+    let names = ["POWER", "BLOCK", "MAGIC", "FAITH", "SPEED"];
+    let mut points = [0; 5];
+
+    for (name, value) in names.iter().zip(given) {
+        if value.is_some_and(|value| !(STAT_MIN..=STAT_MAX).contains(&value)) {
+            return Err(ClrpgError::InvalidChoice(
+                format!("{} has to be between {} and {}.", name, STAT_MIN, STAT_MAX)
+            ));
+        }
+    }
+
+    // points held back for the stats from the given one on: what their flag
+    // says, or the minimum for the ones still to be asked for
+    let reserved = |from: usize| given[from..].iter().map(|value| value.unwrap_or(STAT_MIN)).sum::<i32>();
+    if reserved(0) > POINT_BUY_BUDGET {
+        return Err(ClrpgError::InvalidChoice(
+            format!("{} points are needed, but only {} are available.", reserved(0), POINT_BUY_BUDGET)
+        ));
+    }
+
+    for i in 0..points.len() {
+        let left = POINT_BUY_BUDGET - points[..i].iter().sum::<i32>();
+        let cap = (left - reserved(i + 1)).min(STAT_MAX);
+        points[i] = match given[i] {
+            Some(value) => value,
+            None => loop {
+                let question = format!("{} ({}-{}, {} points left):", names[i], STAT_MIN, cap, left);
+                let answer = prompt(&question).ok_or_else(|| {
+                    ClrpgError::InvalidChoice(format!("No points were given for {}.", names[i]))
+                })?;
+                match answer.parse::<i32>() {
+                    Ok(value) if (STAT_MIN..=cap).contains(&value) => break value,
+                    _ => errln!("{} is not a valid amount of points", answer),
+                }
+            },
+        };
+    }
+
+    let spent = points.iter().sum::<i32>();
+    if spent < POINT_BUY_BUDGET {
+        warnln!("{} points were left unspent", POINT_BUY_BUDGET - spent);
+    }
+
+    Ok(points)
+}
+
+fn create_new(new: &New) -> Result<String, ClrpgError> {
     require_root()?;
     require_char()?;
 
+    let class = match &new.class {
+        Some(class) => Some(require_class(class)?),
+        None => None,
+    };
+
+    // a set that cannot be picked fails before anything is rolled
+    match (new.reroll, new.pick) {
+        (Some(0), _) => return Err(ClrpgError::InvalidChoice("At least one set has to be rolled.".to_string())),
+        (Some(rolls), Some(picked)) if !(1..=rolls).contains(&picked) => {
+            return Err(ClrpgError::InvalidChoice(format!("There is no set {} out of {}.", picked, rolls)));
+        },
+        _ => (),
+    }
+
     let mut state = RandomState::single_use()?;
 
     let name = match &new.name {
@@ -60,39 +277,45 @@ fn create_new(new: &New) -> Result<String, String> {
     let id = state.generate_id();
 
     if check_character(&name)? {
-        return Err(format!("Character {} already exist!", name.yellow().bold()));
+        return Err(ClrpgError::CharacterExists { name });
     }
 
-    create_character(id.clone(), name.clone(), &mut state)?;
+    let seed = require_meta()?.seed;
+    let (stats, creation) = choose_stats(new, class.as_ref(), seed, &id, &mut state)?;
+    let inventory = Inventory::starting_kit(&mut state);
+    create_character(id.clone(), name.clone(), class.as_ref(), stats, creation, inventory)?;
     infoln!("Created {}", name.yellow().bold());
     Ok(name)
 }
 
-pub fn process_new(new: &New) {
+pub fn process_new(new: &New) -> Result<(), ClrpgError> {
     infoln!("Creating new character...");
     match create_new(new) {
         Ok(s) => {
             infoln!("{}", "Create character succeeded.");
-            println!("The adventurer {} walks into the tavern.", s.bold());
+            print_message(&format!("The adventurer {} walks into the tavern.", s.bold()));
+            Ok(())
         }
         Err(err) => {
             errln!("{}", "Create character failed.");
-            println!("{}", err.red());
+            Err(err)
         }
     }
 }
 
-fn list_characters(list: &List) -> Result<String, String> {
+fn list_characters() -> Result<Vec<CharacterObj>, ClrpgError> {
     require_root()?;
     require_char()?;
 
-    let chars = load_characters();
+    Ok(load_characters())
+}
 
+fn render_table(chars: Vec<CharacterObj>, list: &List) -> String {
     let style =
         Style::blank().lines([(1, Style::markdown().get_horizontal().horizontal(Some('-')))]);
 
-    let table = Table::new(chars)
-        .with(Disable::Column(if list.all { 4.. } else { 3.. }))
+    Table::new(chars)
+        .with(Disable::Column(if list.all { 7.. } else { 6.. }))
         .with(style)
         .with(Margin::new(0, 0, 1, 1).set_fill(' ', ' ', ' ', ' '))
         .with(Modify::new(Rows::first()).with(str::to_uppercase))
@@ -100,110 +323,523 @@ fn list_characters(list: &List) -> Result<String, String> {
             Modify::new(Columns::single(1).not(Rows::first()))
                 .with(|s: &str| s.yellow().to_string()),
         )
-        .to_string();
-
-    Ok(table)
+        .to_string()
 }
 
-pub fn process_list(list: &List) {
+pub fn process_list(list: &List) -> Result<(), ClrpgError> {
     infoln!("Listing characters...");
-    match list_characters(list) {
-        Ok(s) => {
+    match list_characters() {
+        Ok(chars) => {
             infoln!("{}", "List characters succeeded.");
-            println!("{}", s);
+            if is_json() {
+                print_json(&chars);
+            } else {
+                println!("{}", render_table(chars, list));
+            }
+            Ok(())
         }
         Err(err) => {
-            errln!("{}", err);
             errln!("{}", "List characters failed.");
+            Err(err)
         }
     }
 }
 
-pub fn wait_character(wait: &Wait) -> Result<String, String> {
+pub fn wait_character(wait: &Wait) -> Result<String, ClrpgError> {
 
     require_root()?;
-    let char_obj = match require_character(&wait.name) {
-        Ok(c) => c,
-        Err(_) => {
-            return Err(
-                format!(
-                    "The adventurer {} is not in the tavern.", 
-                    wait.name.bold()
-                )
-            )
-        },
-    };
+    if !check_character(&wait.name)? {
+        return Err(ClrpgError::MissingCharacter { name: wait.name.clone() });
+    }
 
+    let mut char_obj = require_character(&wait.name)?;
     if !char_obj.is_alive() {
         errln!("{} is dead", char_obj.get_name());
-        return Err(
-            format!(
-                "{} is dead!", 
-                char_obj.get_name().bold()
-            )
-        )
+        return Err(ClrpgError::DeadCharacter { name: wait.name.clone() });
     }
 
 
     let mut meta = require_meta()?;
+    meta.check(Transition::Wait)?;
 
-    if let Some(character) = meta.current {
-        if character == wait.name {
-            errln!("{} is already waiting", character);
-            return Ok(
-                format!(
-                    "{} is already waiting at the mouth of the dungeon.", 
-                    character.bold()
-                )
-            )
-        } else if meta.status != MetaStatus::OutsideTheDungeon {
-            errln!("{} is already inside the dungeon", character);
-            return Err(
-                format!(
-                    "{} is already attempting the dungeon.", 
-                    character.bold()
-                )
+    if meta.in_party(&wait.name) {
+        errln!("{} is already waiting", wait.name);
+        return Ok(
+            format!(
+                "{} is already waiting at the mouth of the dungeon.", 
+                wait.name.bold()
             )
-        }  else {
-            warnln!("{} is already waiting", character);
-            warnln!("Replacing {} with {}", character, wait.name);
+        )
+    }
+    if meta.party().len() >= PARTY_SIZE {
+        return Err(ClrpgError::PartyFull);
+    }
 
-        }
+    let mut story = String::new();
+    if char_obj.get_status() == CharacterStatus::Resting {
+        let healed = char_obj.wake();
+        write_character(&char_obj)?;
+        story = match healed {
+            0 => format!("{} wakes up refreshed. ", wait.name.bold()),
+            n => format!("{} wakes up refreshed, recovering {} health. ", wait.name.bold(), n),
+        };
     }
 
-    meta.current = Some(wait.name.clone());
-    meta.status = MetaStatus::OutsideTheDungeon;
-    write_meta(&meta)?;
+    meta.join(&wait.name)?;
+    write_meta(&mut meta)?;
 
     Ok(
         format!(
-            "{} stands outside the dungeon, awaiting a glorious adventure!", 
-            wait.name.bold()
+            "{}{} stands outside the dungeon, awaiting a glorious adventure!", 
+            story, wait.name.bold()
         )
     )
 }
 
-pub fn process_wait(wait: &Wait) {
+pub fn process_wait(wait: &Wait) -> Result<(), ClrpgError> {
     infoln!("Waiting character");
 
-
-    
     match wait_character(wait) {
         Ok(s) => {
             infoln!("{}", "Waiting character succeeded.");
-            println!("{}", s);
+            print_message(&s);
+            Ok(())
         }
         Err(err) => {
             errln!("{}", "Waiting character failed.");
-            println!("{}", err.red());
+            Err(err)
         }
     }
 }
 
-pub fn process_character(character: &Character) {
+#[derive(Serialize)]
+struct Sheet {
+    character: CharacterObj,
+    runs: Vec<Run>,
+}
+
+fn show_character(show: &Show) -> Result<Sheet, ClrpgError> {
+    require_root()?;
+    if !check_character(&show.name)? {
+        return Err(ClrpgError::MissingCharacter { name: show.name.clone() });
+    }
+
+    let character = require_character(&show.name)?;
+    let runs = runs_of(&show.name, &load_history());
+
+    Ok(Sheet { character, runs })
+}
+
+fn render_sheet(sheet: &Sheet) -> String {
+    let mut s = format!(
+        "{:>3}   {} {} {}\n",
+        sheet.character,
+        "Created".bold(),
+        sheet.character.get_created().format("%Y-%m-%d %H:%M"),
+        format!("({})", sheet.character.get_creation()).black()
+    );
+
+    s.push_str(&format!("\n   {}\n", "Modifiers".bold()));
+    // equipment bonuses are listed along with the equipment instead
+    let modifiers: Vec<_> = sheet.character.stats().modifiers()
+        .into_iter()
+        .filter(|(_, m)| !m.is_lasting())
+        .collect();
+    if modifiers.is_empty() {
+        s.push_str(&format!("   {}\n", "No temporary modifiers".black()));
+    }
+    for (stat, modifier) in modifiers {
+        let amount = if modifier.amount < 0 {
+            format!("{:+}", modifier.amount).red()
+        } else {
+            format!("{:+}", modifier.amount).green()
+        };
+        let turns = modifier.turns;
+        s.push_str(&format!(
+            "   {:<7} {:<3} {} {}\n",
+            format!("{}:", stat), amount, modifier.name,
+            format!("({} turn{} left)", turns, if turns == 1 { "" } else { "s" }).black()
+        ));
+    }
+
+    s.push_str(&format!("\n{}", sheet.character.inventory()));
+
+    s.push_str(&format!("\n   {}\n", "Runs".bold()));
+    if sheet.runs.is_empty() {
+        s.push_str(&format!("   {}\n", "Has not set foot in the dungeon yet".black()));
+    }
+    for (i, run) in sheet.runs.iter().enumerate() {
+        s.push_str(&format!(
+            "   #{:<3} {}  {:>3} {:<9}  {}\n",
+            i + 1,
+            run.entered.format("%Y-%m-%d %H:%M"),
+            run.victories,
+            if run.victories == 1 { "victory" } else { "victories" },
+            run.outcome
+        ));
+    }
+
+    s
+}
+
+pub fn process_show(show: &Show) -> Result<(), ClrpgError> {
+    match show_character(show) {
+        Ok(sheet) => {
+            infoln!("{}", "Show character succeeded.");
+            if is_json() {
+                print_json(&sheet);
+            } else {
+                println!("{}", render_sheet(&sheet));
+            }
+            Ok(())
+        }
+        Err(err) => {
+            errln!("{}", "Show character failed.");
+            Err(err)
+        }
+    }
+}
+
+/// Reads the character, as long as they are not in the party
+fn require_idle(name: &str) -> Result<CharacterObj, ClrpgError> {
+    require_root()?;
+    if !check_character(name)? {
+        return Err(ClrpgError::MissingCharacter { name: name.to_string() });
+    }
+
+    let meta = require_meta()?;
+    if meta.in_party(name) {
+        return Err(ClrpgError::CharacterBusy { name: name.to_string() });
+    }
+
+    require_character(name)
+}
+
+fn delete(delete: &Delete) -> Result<String, ClrpgError> {
+    require_idle(&delete.name)?;
+    delete_character(&delete.name)?;
+    infoln!("Deleted {}", delete.name);
+
+    Ok(format!("{} packs up and leaves the tavern for good.", delete.name.bold()))
+}
+
+pub fn process_delete(del: &Delete) -> Result<(), ClrpgError> {
+    match delete(del) {
+        Ok(s) => {
+            infoln!("{}", "Delete character succeeded.");
+            print_message(&s);
+            Ok(())
+        }
+        Err(err) => {
+            errln!("{}", "Delete character failed.");
+            Err(err)
+        }
+    }
+}
+
+fn retire(retire: &Retire) -> Result<String, ClrpgError> {
+    let character = require_idle(&retire.name)?;
+    retire_character(character)?;
+    infoln!("Moved {} to {}", retire.name, HALL_FOLDER_NAME);
+
+    Ok(
+        format!(
+            "{} hangs up their sword and takes a place in the hall of fame.",
+            retire.name.bold()
+        )
+    )
+}
+
+pub fn process_retire(ret: &Retire) -> Result<(), ClrpgError> {
+    match retire(ret) {
+        Ok(s) => {
+            infoln!("{}", "Retire character succeeded.");
+            print_message(&s);
+            Ok(())
+        }
+        Err(err) => {
+            errln!("{}", "Retire character failed.");
+            Err(err)
+        }
+    }
+}
+
+fn rename(rename: &Rename) -> Result<String, ClrpgError> {
+    let character = require_idle(&rename.name)?;
+    if check_character(&rename.new_name)? {
+        return Err(ClrpgError::CharacterExists { name: rename.new_name.clone() });
+    }
+
+    rename_character(character, &rename.new_name)?;
+
+    Ok(
+        format!(
+            "{} will henceforth be known as {}.",
+            rename.name.bold(), rename.new_name.yellow().bold()
+        )
+    )
+}
+
+pub fn process_rename(ren: &Rename) -> Result<(), ClrpgError> {
+    match rename(ren) {
+        Ok(s) => {
+            infoln!("{}", "Rename character succeeded.");
+            print_message(&s);
+            Ok(())
+        }
+        Err(err) => {
+            errln!("{}", "Rename character failed.");
+            Err(err)
+        }
+    }
+}
+
+fn render_hall(hall: &[HallEntry]) -> String {
+    if hall.is_empty() {
+        return format!("{}", "The hall of fame stands empty.".black());
+    }
+
+    hall.iter()
+        .map(|entry| format!(
+            "   {} ({})  {} {}  RUNS: {}  VICTORIES: {}",
+            entry.character.get_name().yellow().bold(),
+            entry.character.get_id(),
+            "retired".black(),
+            entry.retired.format("%Y-%m-%d"),
+            entry.runs.len(),
+            entry.victories()
+        ))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn process_hall() -> Result<(), ClrpgError> {
+    match require_root() {
+        Ok(_) => {
+            let hall = load_hall();
+            if is_json() {
+                print_json(&hall);
+            } else {
+                println!("{}", render_hall(&hall));
+            }
+            Ok(())
+        }
+        Err(err) => {
+            errln!("{}", "Hall of fame failed.");
+            Err(err)
+        }
+    }
+}
+
+fn revive(revive: &Revive) -> Result<String, ClrpgError> {
+    require_root()?;
+    require_char()?;
+
+    let mut grave = find_grave(&revive.name)
+        .ok_or_else(|| ClrpgError::MissingGrave { name: revive.name.clone() })?;
+    // someone new may have taken the name in the meantime
+    if check_character(&revive.name)? {
+        return Err(ClrpgError::CharacterExists { name: revive.name.clone() });
+    }
+
+    let mut payer = match revive.payer.as_deref().filter(|&payer| payer != revive.name) {
+        Some(name) => {
+            let payer = require_idle(name)?;
+            if !payer.is_alive() {
+                return Err(ClrpgError::DeadCharacter { name: name.to_string() });
+            }
+            Some(payer)
+        },
+        None => None,
+    };
+
+    // nothing is paid for rites that cannot be rolled
+    let mut state = RandomState::single_use()?;
+
+    let cost = grave.revival_cost();
+    let mut chance = grave.revival_chance();
+    let mut treasury = require_treasury()?;
+    let purse = payer.as_mut().unwrap_or(&mut grave.character);
+    let offering = match &revive.item {
+        Some(name) => {
+            let item = purse.inventory_mut().take(name)
+                .ok_or_else(|| ClrpgError::MissingItem { name: purse.get_name().to_string(), item: name.clone() })?;
+            match item.effect {
+                Some(ItemEffect::Revive(bonus)) => chance = (chance + bonus).min(REVIVAL_MAX_CHANCE),
+                _ => return Err(ClrpgError::WrongItem { item: item.name, action: "offered for a revival" }),
+            }
+            format!("the {} from {}'s pack", item, purse.get_name())
+        },
+        None => {
+            spend(purse, cost, Reason::Revival)?;
+            treasury.deposit(cost, purse.get_name(), Reason::Revival);
+            format!("{} gold from {}'s purse", cost, purse.get_name())
+        },
+    };
+    if let Some(payer) = &payer {
+        write_character(payer)?;
+    }
+    write_treasury(&treasury)?;
+
+    let roll = state.rng.gen_range(0..100);
+    debugln!("Rolled {} against a revival chance of {}%", roll, chance);
+
+    let story = format!(
+        "The temple takes {} and the priests chant over the grave of {} ({}% chance).",
+        offering, revive.name.bold(), chance
+    );
+    if roll >= chance {
+        write_grave(&grave)?;
+        return Ok(format!("{} The candles gutter out. {} stays in the grave.", story, revive.name));
+    }
+
+    let (health_lost, xp_lost) = grave.character.revive();
+    unbury_character(grave)?;
+    infoln!("Moved {} back to {}", revive.name, CHAR_FOLDER_NAME);
+
+    Ok(
+        format!(
+            "{} {} gasps back to life, {} maximum health and {} experience poorer.",
+            story, revive.name, health_lost, xp_lost
+        )
+    )
+}
+
+pub fn process_revive(revive_args: &Revive) -> Result<(), ClrpgError> {
+    match revive(revive_args) {
+        Ok(s) => {
+            infoln!("{}", "Revive character succeeded.");
+            print_message(&s);
+            Ok(())
+        }
+        Err(err) => {
+            errln!("{}", "Revive character failed.");
+            Err(err)
+        }
+    }
+}
+
+fn render_graveyard(graveyard: &[Grave]) -> String {
+    if graveyard.is_empty() {
+        return format!("{}", "No one has fallen in the dungeon. Yet.".black());
+    }
+
+    graveyard.iter()
+        .map(|grave| {
+            let place = match grave.floor {
+                Some(floor) => format!("on floor {}", floor),
+                None => "outside the dungeon".to_string(),
+            };
+            let killer = match &grave.killer {
+                Some(killer) => format!(" at the hands of {}", killer.red()),
+                None => String::new(),
+            };
+            format!(
+                "   {} ({})  LEVEL {}  {} {} {}\n      {}{}.",
+                grave.character.get_name().yellow().bold(),
+                grave.character.get_id(),
+                grave.character.get_level(),
+                "died".black(),
+                grave.died.format("%Y-%m-%d"),
+                place,
+                grave.cause,
+                killer
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn process_graveyard() -> Result<(), ClrpgError> {
+    match require_root() {
+        Ok(_) => {
+            let graveyard = load_graveyard();
+            if is_json() {
+                print_json(&graveyard);
+            } else {
+                println!("{}", render_graveyard(&graveyard));
+            }
+            Ok(())
+        }
+        Err(err) => {
+            errln!("{}", "Graveyard failed.");
+            Err(err)
+        }
+    }
+}
+
+fn render_classes(classes: &[CharacterClass]) -> String {
+    classes.iter()
+        .map(|class| {
+            let abilities = class.abilities.iter()
+                .map(|a| a.name.clone())
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!(
+                "   {:<10} {}\n   {:<10} {}",
+                class.name.yellow().bold(), class.description,
+                "", format!("({})", abilities).black()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn process_classes() -> Result<(), ClrpgError> {
+    let classes = load_classes();
+    if is_json() {
+        print_json(&classes);
+    } else {
+        println!("{}", render_classes(&classes));
+    }
+    Ok(())
+}
+
+pub fn process_character(character: &Character) -> Result<(), ClrpgError> {
     match &character.command {
         Subcommands::List(list) => process_list(list),
         Subcommands::New(new) => process_new(new),
         Subcommands::Wait(wait) => process_wait(wait),
+        Subcommands::Show(show) => process_show(show),
+        Subcommands::Delete(delete) => process_delete(delete),
+        Subcommands::Retire(retire) => process_retire(retire),
+        Subcommands::Rename(rename) => process_rename(rename),
+        Subcommands::Hall => process_hall(),
+        Subcommands::Revive(revive) => process_revive(revive),
+        Subcommands::Classes => process_classes(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point_buy(power: Option<i32>, block: Option<i32>, magic: Option<i32>, faith: Option<i32>, speed: Option<i32>) -> New {
+        New { name: None, class: None, point_buy: true, power, block, magic, faith, speed, reroll: None, pick: None }
+    }
+
+    #[test]
+    fn points_given_as_flags_are_bought() {
+        let new = point_buy(Some(19), Some(10), Some(10), Some(6), Some(5));
+        assert_eq!(buy_points(&new).unwrap(), [19, 10, 10, 6, 5]);
+    }
+
+    #[test]
+    fn points_cannot_go_over_the_budget() {
+        let new = point_buy(Some(19), Some(19), Some(10), Some(2), Some(1));
+        assert!(matches!(buy_points(&new), Err(ClrpgError::InvalidChoice(_))));
+
+        // the stats still to be asked for need at least the minimum each
+        let new = point_buy(Some(19), Some(19), Some(10), None, None);
+        assert!(matches!(buy_points(&new), Err(ClrpgError::InvalidChoice(_))));
+    }
+
+    #[test]
+    fn points_have_to_be_within_the_stat_range() {
+        let new = point_buy(Some(STAT_MAX + 1), None, None, None, None);
+        assert!(matches!(buy_points(&new), Err(ClrpgError::InvalidChoice(_))));
+
+        let new = point_buy(Some(STAT_MIN - 1), Some(1), Some(1), Some(1), Some(1));
+        assert!(matches!(buy_points(&new), Err(ClrpgError::InvalidChoice(_))));
     }
 }
@@ -1,15 +1,14 @@
 use crate::{errln, infoln, warnln};
-use crate::utils::common::*;
+use crate::utils::{common::*, error::ClrpgError, print::print_message};
 
-use colored::Colorize;
 
-pub fn process_clean() {
+pub fn process_clean() -> Result<(), ClrpgError> {
     
 
     match check_root() {
         Ok(exist) if !exist => {
             warnln!("Nothing to clean up");
-            return;
+            return Ok(());
         },
         _ => (), // can silently ignore error
     }
@@ -20,12 +19,12 @@ pub fn process_clean() {
             infoln!("Deleted {}", ROOT_FOLDER_NAME);
         },
         Err(err) =>  {
-            errln!("{}", err);
             errln!("Clean failed.");
-            return;
+            return Err(err);
         }
     }
     
     infoln!("Clean succeeded.");
-    println!("{}", "The dungeon has mysteriously vanished.");
+    print_message("The dungeon has mysteriously vanished.");
+    Ok(())
 }
\ No newline at end of file
@@ -1,43 +1,240 @@
-use crate::{infoln};
+use crate::{debugln, errln, infoln};
 use clap::Args;
-use crate::utils::{print::*};
+use crate::utils::{print::*, common::*, error::ClrpgError, bestiary::Monster, combat::*, dungeon::*, ledger::*};
 use colored::Colorize;
+use serde::Serialize;
 
 #[derive(Args)]
 pub struct Door {
-    /// Narration speed; default norm
-    #[clap(short, long, arg_enum, value_name = "SPEED")]
-    narrate_speed: Option<NarrateSpeed>,
-    
-    /// Narration speed; Default norm
-    #[clap(short, long, value_parser)]
-    wrapping: Option<usize>,
+    #[clap(flatten)]
+    narration: NarrateOptions,
 }
 
-const IPSUM: &str = 
+/// What happened behind the door, along with the state it left things in
+#[derive(Serialize)]
+struct Encounter {
+    story: String,
+    status: MetaStatus,
+    /// Members of the party still standing
+    party: Vec<CharacterObj>,
+    combat: Option<Combat>,
+}
 
-"Lorem ipsum dolor sit amet, consectetur adipiscing elit. Mauris posuere auctor libero ac vulputate. Nunc a mollis dolor. Proin malesuada a turpis non imperdiet. 
-";   
+/// Leads the waiting party through the dungeon door onto the first floor
+fn enter_dungeon(meta: &mut Meta, party: &mut [CharacterObj]) -> Result<String, ClrpgError> {
+    for member in party.iter() {
+        if let Some(status) = member.unfit() {
+            return Err(ClrpgError::Unfit { name: member.get_name().to_string(), status });
+        }
+    }
 
+    let floor = require_floor(meta, 1)?;
+    let position = floor.entrance_position();
+    infoln!("Entering at {}", position);
 
+    let name = meta.party_name().unwrap_or_default();
+    meta.transition(Transition::Enter)?;
+    meta.position = Some(position);
+    write_meta(meta)?;
+    for member in party.iter_mut() {
+        member.reach(floor.depth);
+        write_character(member)?;
+    }
 
+    Ok(
+        format!(
+            "{} pushes open the heavy door and steps into the dungeon. The air is damp and cold.",
+            name
+        )
+    )
+}
 
-pub fn process_door(door: &Door) {
-    
-    infoln!("Opening Door...");
+/// Opens up the floor below the one whose boss just fell, or clears the
+/// dungeon if it was the final floor. Returns the news.
+fn unlock_below(meta: &mut Meta, depth: u32) -> String {
+    if depth >= FINAL_FLOOR {
+        meta.cleared = true;
+        return format!("{}", "The last master of the dungeon has fallen. The dungeon is cleared!".green().bold());
+    }
 
-    let speed = match &door.narrate_speed {
-        Some(s) => s.clone(),
-        _ => NarrateSpeed::Norm
-    };
+    meta.deepest = meta.deepest.max(depth + 1);
+    format!("The way down to floor {} lies open.", depth + 1)
+}
+
+/// Kicks open a side door, letting a wandering monster in
+fn start_encounter(meta: &mut Meta) -> Result<(String, Combat), ClrpgError> {
+    let mut state = RandomState::single_use()?;
+    let name = meta.party_name().unwrap_or_default();
+    let combat = begin_combat(meta, Monster::from_rng(&mut state))?;
+
+    Ok((
+        format!(
+            "{} kicks open the door! {} blocks the way, ready for a fight.",
+            name, combat.enemy().get_name()
+        ),
+        combat
+    ))
+}
+
+/// Resolves the next round of the ongoing encounter. Members who fall are
+/// buried and leave the party, which is defeated once no one is left.
+fn fight_round(meta: &mut Meta, party: &mut Vec<CharacterObj>) -> Result<(String, Combat), ClrpgError> {
+    let mut combat = require_combat()?;
+    let mut state = RandomState::single_use()?;
+
+    let outcome = combat.resolve_round(party, &mut state);
+
+    let mut story = combat.last_round()
+        .map(|round| {
+            round.attacks.iter()
+                .map(|attack| attack.to_string())
+                .chain(round.ticks.iter().map(|tick| tick.to_string()))
+                .chain(round.phases.iter().cloned())
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .unwrap_or_default();
+
+    let floor = meta.position.as_ref().map(|position| position.floor);
+    for fallen in party.iter().filter(|member| !member.is_alive()) {
+        let name = fallen.get_name();
+        meta.part(name, Transition::Defeat)?;
+
+        let cause = combat.cause_of_death(name);
+        let killer = Some(combat.enemy().get_name().to_string());
+        bury_character(fallen.clone(), cause, floor, killer)?;
+        infoln!("Moved {} to {}", name, GRAVEYARD_FOLDER_NAME);
+        story = format!(
+            "{} {} collapses to the ground and breathes their last. They are laid to rest in the graveyard.",
+            story, name
+        );
+    }
+    party.retain(CharacterObj::is_alive);
+
+    match outcome {
+        CombatOutcome::Ongoing => {
+            write_combat(&combat)?;
+        },
+        CombatOutcome::Victory => {
+            delete_combat()?;
+            let mut unlocked = None;
+            if let Some(position) = &meta.position {
+                let mut floor = require_floor(meta, position.floor)?;
+                if floor.room(position.room).is_occupied() {
+                    floor.rooms[position.room].cleared = true;
+                    write_floor(&floor)?;
+                }
+                if combat.enemy().boss().is_some() {
+                    unlocked = Some(unlock_below(meta, floor.depth));
+                }
+            }
+            meta.transition(Transition::Victory)?;
 
-    let wrap = match &door.wrapping {
-        Some(v) => *v,
-        _ => 75usize
+            let name = meta.party_name().unwrap_or_default();
+            story = match combat.enemy().boss() {
+                Some(script) => format!("{} {} {} stands victorious.", story, script.defeat, name),
+                None => format!(
+                    "{} The {} falls! {} stands victorious.",
+                    story, combat.enemy().get_name(), name
+                ),
+            };
+            if let Some(unlocked) = unlocked {
+                story = format!("{} {}", story, unlocked);
+            }
+
+            // the experience is shared out, rounding up
+            let xp = combat.enemy().get_tier().xp();
+            let share = xp.div_ceil(party.len() as u32);
+            story = match party.len() {
+                1 => format!("{} {} earns {} experience.", story, name, share),
+                _ => format!("{} Everyone earns {} experience.", story, share),
+            };
+            for member in party.iter_mut() {
+                if member.gain_xp(share, &mut state) > 0 {
+                    story = format!("{} {} reaches level {}!", story, member.get_name(), member.get_level());
+                }
+            }
+
+            let loot = combat.enemy().roll_loot(&mut state);
+            debugln!("Rolled loot {:?}", loot);
+            story = format!("{} The {} leaves behind {}.", story, combat.enemy().get_kind(), loot);
+
+            // whoever landed the last blow claims the spoils
+            let claimant = combat.last_striker()
+                .and_then(|striker| party.iter().position(|member| member.get_name() == striker))
+                .unwrap_or(0);
+            let mut treasury = require_treasury()?;
+            let tithe = collect(&mut party[claimant], loot, Reason::Loot, &mut treasury);
+            if party.len() > 1 {
+                story = format!("{} {} claims the spoils.", story, party[claimant].get_name());
+            }
+            if tithe > 0 {
+                story = format!("{} {} gold is tithed to the treasury.", story, tithe);
+            }
+            write_treasury(&treasury)?;
+        },
+        CombatOutcome::Defeat => {
+            delete_combat()?;
+        },
+    }
+    write_meta(meta)?;
+    for member in party.iter() {
+        write_character(member)?;
+    }
+
+    Ok((story, combat))
+}
+
+fn kick_door() -> Result<Encounter, ClrpgError> {
+    require_root()?;
+    let mut meta = require_meta()?;
+
+    let mut party = require_party(&meta)?;
+    if let Some(fallen) = party.iter().find(|member| !member.is_alive()) {
+        errln!("{} is dead", fallen.get_name());
+        return Err(ClrpgError::DeadCharacter { name: fallen.get_name().to_string() });
+    }
+
+    let (story, combat) = match meta.status() {
+        MetaStatus::InCombat => {
+            let (story, combat) = fight_round(&mut meta, &mut party)?;
+            (story, Some(combat))
+        },
+        MetaStatus::InTheDungeon => {
+            let (story, combat) = start_encounter(&mut meta)?;
+            (story, Some(combat))
+        },
+        _ => (enter_dungeon(&mut meta, &mut party)?, None),
     };
 
-    narrate(IPSUM, speed, wrap);
-    
-    infoln!("Done");
+    Ok(Encounter { story, status: meta.status(), party, combat })
+}
+
+pub fn process_door(door: &Door) -> Result<(), ClrpgError> {
 
-}
\ No newline at end of file
+    infoln!("Opening Door...");
+
+    match kick_door() {
+        Ok(encounter) if is_json() => {
+            print_json(&encounter);
+            infoln!("Done");
+            Ok(())
+        },
+        Ok(Encounter { story, party, combat, .. }) => {
+            door.narration.narrate(&story);
+            if let Some(combat) = combat {
+                println!("{}", combat);
+                println!();
+            }
+            for member in party {
+                println!("{}\n{:3}", member.get_name().bold(), member.stats().health());
+            }
+            infoln!("Done");
+            Ok(())
+        },
+        Err(err) => {
+            errln!("{}", "Opening door failed.");
+            Err(err)
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use crate::{debugln, errln, infoln};
+use crate::utils::{print::*, common::*, error::ClrpgError, bestiary::Monster, combat::*, dungeon::*, items::Loot, ledger::*};
+
+use clap::Args;
+use colored::Colorize;
+
+#[derive(Args)]
+pub struct Move {
+    /// Direction to head in
+    #[clap(arg_enum)]
+    direction: Direction,
+
+    #[clap(flatten)]
+    narration: NarrateOptions,
+}
+
+#[derive(Args)]
+pub struct Open {
+    #[clap(flatten)]
+    narration: NarrateOptions,
+}
+
+#[derive(Args)]
+pub struct Descend {
+    #[clap(flatten)]
+    narration: NarrateOptions,
+}
+
+#[derive(Args)]
+pub struct Look {
+    #[clap(flatten)]
+    narration: NarrateOptions,
+}
+
+/// Returns how the party is called and its position, as long as it is
+/// somewhere inside the dungeon
+fn require_position(meta: &Meta) -> Result<(String, Position), ClrpgError> {
+    let name = match meta.party_name() {
+        Some(name) => name,
+        None => return Err(ClrpgError::NoAdventurer),
+    };
+
+    meta.require_status(&[MetaStatus::InTheDungeon, MetaStatus::InCombat], Transition::Explore)?;
+
+    match &meta.position {
+        Some(position) => Ok((name, position.clone())),
+        None => {
+            errln!("{} has no recorded position", name);
+            Err(ClrpgError::Unexpected(format!("{} has no recorded position", name)))
+        },
+    }
+}
+
+fn move_character(mov: &Move) -> Result<String, ClrpgError> {
+    require_root()?;
+    let mut meta = require_meta()?;
+    let (name, position) = require_position(&meta)?;
+    meta.transition(Transition::Explore)?;
+
+    let mut floor = require_floor(&meta, position.floor)?;
+    let next = match floor.neighbour(position.room, mov.direction) {
+        Some(next) => next,
+        None => return Err(ClrpgError::NoPassage(mov.direction)),
+    };
+
+    let mut state = RandomState::single_use()?;
+    let discovered = !floor.rooms[next].visited;
+    floor.rooms[next].visited = true;
+    debugln!("Moved from room {} to room {}", position.room, next);
+
+    let room = floor.room(next).clone();
+    let mut story = format!(
+        "{} heads {}. {}",
+        name, mov.direction, floor.describe(next)
+    );
+
+    // a campfire only lasts for a single rest, shared by the whole party
+    let campfire = room.kind == RoomKind::Rest && !room.cleared;
+    for mut member in require_party(&meta)? {
+        let member_name = member.get_name().to_string();
+        if discovered && member.gain_xp(EXPLORE_XP, &mut state) > 0 {
+            story = format!("{} {} reaches level {}!", story, member_name, member.get_level());
+        }
+        if campfire {
+            let healed = member.stats_mut().health_mut().heal(CAMPFIRE_HEAL);
+            story = format!("{} {} rests by the fire, recovering {} health.", story, member_name, healed);
+        }
+        for tick in member.stats_mut().tick(&member_name, false) {
+            story = format!("{} {}", story, tick);
+        }
+        member.tire();
+        member.refresh_status();
+        write_character(&member)?;
+    }
+    if campfire {
+        floor.rooms[next].cleared = true;
+        story = format!("{} The fire burns down to embers.", story);
+    }
+    write_floor(&floor)?;
+
+    meta.position = Some(Position { floor: position.floor, room: next });
+    if room.is_occupied() && room.kind == RoomKind::Boss {
+        let boss = Monster::floor_boss(meta.seed, floor.depth);
+        let combat = begin_combat(&mut meta, boss)?;
+        let boss = combat.enemy();
+        story = format!(
+            "{} {} {}, the {}, rises to meet you!",
+            story, boss.boss().map_or("", |script| script.intro.as_str()), boss.get_name(), boss.get_kind()
+        );
+    } else if room.is_occupied() {
+        let combat = begin_combat(&mut meta, Monster::from_rng(&mut state))?;
+        story = format!("{} {} lunges out of the darkness!", story, combat.enemy().get_name());
+    } else {
+        write_meta(&mut meta)?;
+    }
+
+    Ok(story)
+}
+
+pub fn process_move(mov: &Move) -> Result<(), ClrpgError> {
+    infoln!("Moving character...");
+    match move_character(mov) {
+        Ok(s) => {
+            infoln!("{}", "Moving character succeeded.");
+            mov.narration.narrate(&s);
+            Ok(())
+        }
+        Err(err) => {
+            errln!("{}", "Moving character failed.");
+            Err(err)
+        }
+    }
+}
+
+fn open_chest() -> Result<String, ClrpgError> {
+    require_root()?;
+    let meta = require_meta()?;
+    let (_, position) = require_position(&meta)?;
+    meta.require_status(&[MetaStatus::InTheDungeon], Transition::Explore)?;
+
+    let mut floor = require_floor(&meta, position.floor)?;
+    let room = floor.room(position.room);
+    if room.kind != RoomKind::Treasure || room.cleared {
+        return Err(ClrpgError::NoChest);
+    }
+
+    let mut state = RandomState::single_use()?;
+    let loot = Loot::from_treasure(&mut state, floor.depth);
+    debugln!("Rolled treasure {:?}", loot);
+    floor.rooms[position.room].cleared = true;
+    write_floor(&floor)?;
+
+    // the leader opens the chest and keeps what is inside
+    let mut character = require_party(&meta)?.remove(0);
+    let mut story = format!("{} pries the chest open and finds {}.", character.get_name(), loot);
+    let mut treasury = require_treasury()?;
+    let tithe = collect(&mut character, loot, Reason::Treasure, &mut treasury);
+    if tithe > 0 {
+        story = format!("{} {} gold is tithed to the treasury.", story, tithe);
+    }
+    write_character(&character)?;
+    write_treasury(&treasury)?;
+
+    Ok(story)
+}
+
+pub fn process_open(open: &Open) -> Result<(), ClrpgError> {
+    infoln!("Opening chest...");
+    match open_chest() {
+        Ok(s) => {
+            infoln!("{}", "Opening chest succeeded.");
+            open.narration.narrate(&s);
+            Ok(())
+        }
+        Err(err) => {
+            errln!("{}", "Opening chest failed.");
+            Err(err)
+        }
+    }
+}
+
+/// Takes the stairs in the lair of a fallen boss down to the entrance of
+/// the next floor
+fn take_stairs() -> Result<String, ClrpgError> {
+    require_root()?;
+    let mut meta = require_meta()?;
+    let (name, position) = require_position(&meta)?;
+    meta.transition(Transition::Explore)?;
+
+    let floor = require_floor(&meta, position.floor)?;
+    let room = floor.room(position.room);
+    if room.kind != RoomKind::Boss || !room.cleared || position.floor >= meta.deepest {
+        return Err(ClrpgError::NoStairs);
+    }
+
+    let below = require_floor(&meta, position.floor + 1)?;
+    meta.position = Some(below.entrance_position());
+    write_meta(&mut meta)?;
+    debugln!("Descended to floor {}", below.depth);
+
+    for mut member in require_party(&meta)? {
+        member.reach(below.depth);
+        member.tire();
+        member.refresh_status();
+        write_character(&member)?;
+    }
+
+    Ok(
+        format!(
+            "{} descends the stairs to floor {}. {}",
+            name, below.depth, below.describe(below.entrance)
+        )
+    )
+}
+
+pub fn process_descend(descend: &Descend) -> Result<(), ClrpgError> {
+    infoln!("Descending...");
+    match take_stairs() {
+        Ok(s) => {
+            infoln!("{}", "Descending succeeded.");
+            descend.narration.narrate(&s);
+            Ok(())
+        }
+        Err(err) => {
+            errln!("{}", "Descending failed.");
+            Err(err)
+        }
+    }
+}
+
+fn look_around() -> Result<String, ClrpgError> {
+    require_root()?;
+    let meta = require_meta()?;
+    let (name, position) = require_position(&meta)?;
+
+    let floor = require_floor(&meta, position.floor)?;
+
+    let mut story = format!("{} looks around. {}", name, floor.describe(position.room));
+
+    if meta.status() == MetaStatus::InCombat {
+        let combat = require_combat()?;
+        story = format!("{} {} stands ready to strike.", story, combat.enemy().get_name());
+    }
+
+    let exits = floor.exits(position.room)
+        .iter()
+        .map(|dir| dir.to_string())
+        .collect::<Vec<String>>();
+
+    story = match exits.len() {
+        0 => format!("{} There is no way onwards.", story),
+        1 => format!("{} A passage leads {}.", story, exits[0]),
+        n => format!(
+            "{} Passages lead {} and {}.",
+            story, exits[..n - 1].join(", "), exits[n - 1]
+        ),
+    };
+
+    Ok(story)
+}
+
+pub fn process_look(look: &Look) -> Result<(), ClrpgError> {
+    match look_around() {
+        Ok(s) => {
+            look.narration.narrate(&s);
+            Ok(())
+        },
+        Err(err) => {
+            errln!("{}", "Look failed.");
+            Err(err)
+        }
+    }
+}
+
+fn draw_map() -> Result<String, ClrpgError> {
+    require_root()?;
+    let meta = require_meta()?;
+    let (_, position) = require_position(&meta)?;
+
+    let floor = require_floor(&meta, position.floor)?;
+
+    Ok(
+        format!(
+            "{} {}\n\n{}\n{}",
+            "Floor".bold(), floor.depth,
+            floor.render_map(Some(position.room)),
+            "(@ you, ? unexplored, E entrance, C combat, T treasure, R rest, S shop, B boss)".black()
+        )
+    )
+}
+
+pub fn process_map() -> Result<(), ClrpgError> {
+    match draw_map() {
+        Ok(s) => {
+            print_message(&s);
+            Ok(())
+        },
+        Err(err) => {
+            errln!("{}", "Map failed.");
+            Err(err)
+        }
+    }
+}
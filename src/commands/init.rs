@@ -1,10 +1,7 @@
 use crate::{errln, warnln, infoln};
-use crate::utils::common::*;
-
-use std::fs::{remove_dir_all};
+use crate::utils::{common::*, error::ClrpgError, dungeon::create_floors, ledger::create_treasury, print::print_message};
 
 use clap::Args;
-use colored::Colorize;
 
 #[derive(Args)]
 pub struct Init {
@@ -22,16 +19,17 @@ pub struct Init {
 }
 
 
-fn create(init: &Init) -> Result<bool, String> {
+fn create(init: &Init) -> Result<bool, ClrpgError> {
     let mut destroyed = false;
     if check_root()? { // found existing
         if init.force || init.reset { // --force or --reset flag set 
             warnln!("Found existing {0}. Cleaning...", ROOT_FOLDER_NAME);
-            remove_dir_all(ROOT_FOLDER_NAME).unwrap();
+            delete_root()?;
             warnln!("Deleted {}", ROOT_FOLDER_NAME);
             destroyed = true;
         } else { // return err
-            return Err(format!("{} already exists", ROOT_FOLDER_NAME));
+            errln!("{} already exists", ROOT_FOLDER_NAME);
+            return Err(ClrpgError::RootExists);
         }
     } 
     infoln!("Creating...");
@@ -49,30 +47,30 @@ fn create(init: &Init) -> Result<bool, String> {
     create_meta(Meta::new(seed))?;
     infoln!("Created {}", META_FILE_NAME);
 
+    create_floors(seed)?;
+    infoln!("Created {}", FLOORS_FOLDER_NAME);
+
+    create_treasury()?;
+    infoln!("Created {}", TREASURY_FILE_NAME);
+
 
     Ok(destroyed)
 }
 
 
-pub fn reset(init: &mut Init) -> Result<(), String> {
+pub fn reset(init: &mut Init) -> Result<(), ClrpgError> {
     require_root()?;
     let meta = require_meta()?;
 
     infoln!("Found current seed={}", meta.seed);
     init.seed = Some(meta.seed);
 
-    match create(&init) {
-        Ok(_) => (),
-        Err(err) => {
-            errln!("{}", err);
-            return Err(format!("{}", "Failed to reset the dungeon."));
-        }
-    }
+    create(init)?;
 
     Ok(())
 }
 
-pub fn process_init(init: &mut Init) {
+pub fn process_init(init: &mut Init) -> Result<(), ClrpgError> {
     
     //print_logo();    
     
@@ -81,32 +79,30 @@ pub fn process_init(init: &mut Init) {
         match reset(init) {
             Ok(_) => {
                 infoln!("{}", "Reset succeeded.");
-                println!("{}", "The dungeon seems to have reverted to its original state!");
+                print_message("The dungeon seems to have reverted to its original state!");
+                Ok(())
             },
             Err(err) => {
                 errln!("{}", "Reset failed.");
-                println!("{}", err.red());
+                Err(err)
             }
         }
     } else {
         infoln!("Initializing...");
-        match create(&init) {
+        match create(init) {
             Ok(destroyed) => {
                 infoln!("{}", "Init succeeded.");
                 if destroyed {
-                    println!("{}", "The dungeon crumbles as a new one takes its place!");
+                    print_message("The dungeon crumbles as a new one takes its place!");
                 } else {
-                    println!("{}", "A dungeon has appeared!");
+                    print_message("A dungeon has appeared!");
                 }
+                Ok(())
             },
             Err(err) => {
-                errln!("{}", err);
                 errln!("{}", "Init failed.");
-                println!("{}", "Failed to create the dungeon.".red());
+                Err(err)
             }
         }
     }
-
-    
-
 }
\ No newline at end of file
@@ -0,0 +1,180 @@
+use crate::{errln, infoln};
+use crate::utils::{common::*, error::ClrpgError, items::{ItemEffect, ItemKind}, print::*};
+
+use clap::Args;
+use colored::Colorize;
+
+#[derive(Args)]
+pub struct Inventory {
+    /// Character to look at; the party leader if not given.
+    #[clap(short, long, value_parser)]
+    character: Option<String>,
+}
+
+#[derive(Args)]
+pub struct Equip {
+    /// Item to put on.
+    #[clap(value_parser)]
+    item: String,
+
+    /// Character to equip; the party leader if not given.
+    #[clap(short, long, value_parser)]
+    character: Option<String>,
+}
+
+#[derive(Args)]
+pub struct Unequip {
+    /// Item to take off.
+    #[clap(value_parser)]
+    item: String,
+
+    /// Character to unequip; the party leader if not given.
+    #[clap(short, long, value_parser)]
+    character: Option<String>,
+}
+
+#[derive(Args)]
+pub struct Use {
+    /// Item to use.
+    #[clap(value_parser)]
+    item: String,
+
+    /// Character using the item; the party leader if not given.
+    #[clap(short, long, value_parser)]
+    character: Option<String>,
+}
+
+/// Gear can only be changed out of combat
+const CHANGING_GEAR: [MetaStatus; 2] = [MetaStatus::OutsideTheDungeon, MetaStatus::InTheDungeon];
+
+/// Reads the character about to handle their items, who has to be alive
+/// and, if they are in the party, in one of the allowed statuses
+fn require_handler(name: &Option<String>, allowed: &[MetaStatus]) -> Result<CharacterObj, ClrpgError> {
+    let (character, meta) = require_owner(name.as_deref())?;
+    if !character.is_alive() {
+        return Err(ClrpgError::DeadCharacter { name: character.get_name().to_string() });
+    }
+
+    if meta.in_party(character.get_name()) {
+        meta.require_status(allowed, Transition::Explore)?;
+    }
+    Ok(character)
+}
+
+pub fn process_inventory(inventory: &Inventory) -> Result<(), ClrpgError> {
+    match require_owner(inventory.character.as_deref()) {
+        Ok((character, _)) => {
+            infoln!("{}", "Inventory succeeded.");
+            if is_json() {
+                print_json(character.inventory());
+            } else {
+                println!("{}\n\n{}", character.get_name().bold(), character.inventory());
+            }
+            Ok(())
+        },
+        Err(err) => {
+            errln!("{}", "Inventory failed.");
+            Err(err)
+        }
+    }
+}
+
+fn equip_item(equip: &Equip) -> Result<String, ClrpgError> {
+    let mut character = require_handler(&equip.character, &CHANGING_GEAR)?;
+    let name = character.get_name().to_string();
+
+    let item = character.inventory_mut().take(&equip.item)
+        .ok_or_else(|| ClrpgError::MissingItem { name: name.clone(), item: equip.item.clone() })?;
+    if !item.kind.is_equipment() {
+        return Err(ClrpgError::WrongItem { item: item.name, action: "equipped" });
+    }
+
+    let mut story = format!("{} equips the {}.", name.bold(), item.name);
+    if let Some(replaced) = character.equip(item) {
+        story = format!("{} The {} goes back into the pack.", story, replaced.name);
+        character.inventory_mut().add(replaced);
+    }
+    write_character(&character)?;
+
+    Ok(story)
+}
+
+pub fn process_equip(equip: &Equip) -> Result<(), ClrpgError> {
+    match equip_item(equip) {
+        Ok(s) => {
+            infoln!("{}", "Equip succeeded.");
+            print_message(&s);
+            Ok(())
+        },
+        Err(err) => {
+            errln!("{}", "Equip failed.");
+            Err(err)
+        }
+    }
+}
+
+fn unequip_item(unequip: &Unequip) -> Result<String, ClrpgError> {
+    let mut character = require_handler(&unequip.character, &CHANGING_GEAR)?;
+    let name = character.get_name().to_string();
+
+    let item = character.unequip(&unequip.item)
+        .ok_or_else(|| ClrpgError::MissingItem { name: name.clone(), item: unequip.item.clone() })?;
+    let story = format!("{} takes off the {} and packs it away.", name.bold(), item.name);
+    character.inventory_mut().add(item);
+    write_character(&character)?;
+
+    Ok(story)
+}
+
+pub fn process_unequip(unequip: &Unequip) -> Result<(), ClrpgError> {
+    match unequip_item(unequip) {
+        Ok(s) => {
+            infoln!("{}", "Unequip succeeded.");
+            print_message(&s);
+            Ok(())
+        },
+        Err(err) => {
+            errln!("{}", "Unequip failed.");
+            Err(err)
+        }
+    }
+}
+
+fn use_item(usage: &Use) -> Result<String, ClrpgError> {
+    // potions are meant to be downed mid-fight
+    let mut character = require_handler(
+        &usage.character,
+        &[MetaStatus::OutsideTheDungeon, MetaStatus::InTheDungeon, MetaStatus::InCombat]
+    )?;
+    let name = character.get_name().to_string();
+
+    let item = character.inventory_mut().take(&usage.item)
+        .ok_or_else(|| ClrpgError::MissingItem { name: name.clone(), item: usage.item.clone() })?;
+    let effect = match (&item.effect, item.kind) {
+        (Some(ItemEffect::Revive(_)), _) => {
+            return Err(ClrpgError::WrongItem { item: item.name, action: "used on the living" })
+        },
+        (Some(effect), ItemKind::Consumable) => effect.clone(),
+        _ => return Err(ClrpgError::WrongItem { item: item.name, action: "used" }),
+    };
+
+    let outcome = effect.apply(&item.name, character.stats_mut());
+    character.refresh_status();
+    write_character(&character)?;
+
+    Ok(format!("{} uses the {} and {}.", name.bold(), item.name, outcome))
+}
+
+pub fn process_use(usage: &Use) -> Result<(), ClrpgError> {
+    match use_item(usage) {
+        Ok(s) => {
+            infoln!("{}", "Use item succeeded.");
+            print_message(&s);
+            Ok(())
+        },
+        Err(err) => {
+            errln!("{}", "Use item failed.");
+            Err(err)
+        }
+    }
+}
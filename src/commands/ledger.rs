@@ -0,0 +1,59 @@
+use crate::{errln, infoln};
+use crate::utils::{common::*, error::ClrpgError, ledger::*, print::*};
+
+use clap::Args;
+use colored::Colorize;
+use serde::Serialize;
+
+#[derive(Args)]
+pub struct Ledger {
+    /// Only show the transactions of the character.
+    #[clap(short, long, value_parser)]
+    character: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Accounts {
+    treasury: u32,
+    transactions: Vec<Transaction>,
+}
+
+fn read_ledger(ledger: &Ledger) -> Result<Accounts, ClrpgError> {
+    require_root()?;
+    let treasury = require_treasury()?.gold();
+
+    let transactions = load_ledger().into_iter()
+        .filter(|t| ledger.character.is_none() || t.character == ledger.character)
+        .collect();
+
+    Ok(Accounts { treasury, transactions })
+}
+
+fn render_ledger(accounts: &Accounts) -> String {
+    let mut s = format!("{} {} gold\n\n", "Treasury".bold(), accounts.treasury.to_string().yellow());
+    if accounts.transactions.is_empty() {
+        s.push_str(&format!("   {}", "No gold has changed hands yet".black()));
+    }
+    for transaction in &accounts.transactions {
+        s.push_str(&format!("   {}\n", transaction));
+    }
+    s
+}
+
+pub fn process_ledger(ledger: &Ledger) -> Result<(), ClrpgError> {
+    match read_ledger(ledger) {
+        Ok(accounts) => {
+            infoln!("{}", "Ledger succeeded.");
+            if is_json() {
+                print_json(&accounts);
+            } else {
+                println!("{}", render_ledger(&accounts));
+            }
+            Ok(())
+        },
+        Err(err) => {
+            errln!("{}", "Ledger failed.");
+            Err(err)
+        }
+    }
+}
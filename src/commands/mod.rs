@@ -4,12 +4,15 @@ mod clean;
 mod character;
 mod status;
 mod run;
+mod explore;
+mod inventory;
+mod tavern;
+mod ledger;
 
 
 use clap::Subcommand;
-use colored::Colorize;
 
-use crate::{utils::print::print_logo, infoln};
+use crate::{utils::{print::print_logo, error::ClrpgError}, infoln};
 
 #[derive(Subcommand)]
 pub enum Commands {
@@ -28,8 +31,8 @@ pub enum Commands {
     /// Alias for character wait
     Wait(character::Wait),
 
-    /// Run away!
-    Run,
+    /// Run away! (the whole party, or a single member)
+    Run(run::Run),
 
     /// Show the current status
     Status,
@@ -37,25 +40,76 @@ pub enum Commands {
     /// Manage characters
     Character(character::Character),
 
-    /// Kick open the door and face your foe!
+    /// List the adventurers who fell in the dungeon
+    Graveyard,
+
+    /// Kick open the door and face your foe! (fights a round when in combat)
     Door(door::Door),
 
+    /// Head down a passage in the dungeon
+    Move(explore::Move),
+
+    /// Open the treasure chest in the current room
+    Open(explore::Open),
+
+    /// Take the stairs down once the boss of the floor has fallen
+    Descend(explore::Descend),
+
+    /// Look around the current room
+    Look(explore::Look),
+
+    /// Show the explored part of the current floor
+    Map,
+
+    /// Show the equipment and items a character carries
+    Inventory(inventory::Inventory),
+
+    /// Put on a weapon, armor or trinket from the pack
+    Equip(inventory::Equip),
+
+    /// Take off a piece of equipment
+    Unequip(inventory::Unequip),
+
+    /// Use a consumable, like a potion
+    Use(inventory::Use),
+
+    /// Rest, trade and hire adventurers in the tavern
+    Tavern(tavern::Tavern),
+
+    /// Audit the treasury and every movement of gold
+    Ledger(ledger::Ledger),
+
     /// Display logo
     Logo,
 }
 
-pub fn process_command(command: &mut Commands) {
+pub fn process_command(command: &mut Commands) -> Result<(), ClrpgError> {
     infoln!("{}", "Running clrpg...");
     match command {
         Commands::Init(init) => init::process_init(init),
-        Commands::Door(door) => door::process_door(&door),
-        Commands::List(list) => character::process_list(&list),
-        Commands::Nc(new) => character::process_new(&new),
-        Commands::Wait(wait) => character::process_wait(&wait),
+        Commands::Door(door) => door::process_door(door),
+        Commands::List(list) => character::process_list(list),
+        Commands::Nc(new) => character::process_new(new),
+        Commands::Wait(wait) => character::process_wait(wait),
         Commands::Clean => clean::process_clean(),
-        Commands::Character(character) => character::process_character(&character),
-        Commands::Logo => print_logo(),
+        Commands::Character(character) => character::process_character(character),
+        Commands::Logo => {
+            print_logo();
+            Ok(())
+        },
         Commands::Status => status::process_status(),
-        Commands::Run => run::process_run(),
+        Commands::Graveyard => character::process_graveyard(),
+        Commands::Run(run) => run::process_run(run),
+        Commands::Move(mov) => explore::process_move(mov),
+        Commands::Look(look) => explore::process_look(look),
+        Commands::Open(open) => explore::process_open(open),
+        Commands::Descend(descend) => explore::process_descend(descend),
+        Commands::Map => explore::process_map(),
+        Commands::Inventory(inventory) => inventory::process_inventory(inventory),
+        Commands::Equip(equip) => inventory::process_equip(equip),
+        Commands::Unequip(unequip) => inventory::process_unequip(unequip),
+        Commands::Use(usage) => inventory::process_use(usage),
+        Commands::Tavern(tavern) => tavern::process_tavern(tavern),
+        Commands::Ledger(ledger) => ledger::process_ledger(ledger),
     }
 }
\ No newline at end of file
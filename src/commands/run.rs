@@ -1,59 +1,128 @@
 use crate::{errln, infoln};
-use crate::utils::common::*;
+use crate::utils::{common::*, error::ClrpgError, combat::delete_combat, ledger::*, print::*};
 
+use clap::Args;
 use colored::Colorize;
+use serde::Serialize;
 
-fn run_away() -> Result<String, String> {
+#[derive(Args)]
+pub struct Run {
+    /// Member of the party who runs away; the whole party if not given.
+    #[clap(value_parser)]
+    name: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Escape {
+    /// Adventurers who ran away
+    fled: Vec<String>,
+    from: MetaStatus,
+    status: MetaStatus,
+    story: String,
+}
+
+/// Rewards the victories of the run that just ended out of the treasury,
+/// as far as its gold goes
+fn pay_bounty(name: &str) -> Result<Option<String>, ClrpgError> {
+    let victories = runs_of(name, &load_history())
+        .last()
+        .map_or(0, |run| run.victories);
+    let mut treasury = require_treasury()?;
+    let bounty = (victories * BOUNTY_PER_VICTORY).min(treasury.gold());
+    if bounty == 0 {
+        return Ok(None);
+    }
+
+    let mut character = require_character(name)?;
+    treasury.withdraw(bounty, name, Reason::Bounty)?;
+    earn(&mut character, bounty, Reason::Bounty);
+    write_character(&character)?;
+    write_treasury(&treasury)?;
+
+    Ok(Some(format!(
+        "The treasury pays {} a bounty of {} gold for {} {}.",
+        name, bounty, victories, if victories == 1 { "victory" } else { "victories" }
+    )))
+}
+
+fn run_away(run: &Run) -> Result<Escape, ClrpgError> {
     require_root()?;
 
     
     let mut meta = require_meta()?;
 
-    let character = match meta.current {
-        Some(s) => s,
-        None => return Err(
-            format!(
-                "{}", "No one is in the dungeon right now!"
-            )
-        ),
+    if meta.party().is_empty() {
+        return Err(ClrpgError::NoAdventurer);
+    }
+    let (fled, curr_status) = match &run.name {
+        Some(name) if !meta.in_party(name) => {
+            return Err(ClrpgError::NotInParty { name: name.clone() });
+        },
+        Some(name) => (vec![name.clone()], meta.part(name, Transition::Flee)?),
+        None => (meta.party().to_vec(), meta.transition(Transition::Flee)?),
     };
-    let curr_status = meta.status;
-    meta.current = None;
-    meta.status = MetaStatus::HelpWanted;
-    write_meta(&meta)?;
+    if curr_status == MetaStatus::InCombat && meta.party().is_empty() {
+        delete_combat()?;
+    }
+    write_meta(&mut meta)?;
 
+    // the fight is over for whoever got away
+    if curr_status == MetaStatus::InCombat {
+        for name in &fled {
+            let mut character = require_character(name)?;
+            character.stats_mut().clear_stun();
+            character.refresh_status();
+            write_character(&character)?;
+        }
+    }
 
-    let ret = match curr_status {
-        MetaStatus::HelpWanted => return Err("Unexpected error occured".to_string()),
-        MetaStatus::OutsideTheDungeon => format!(
+    let who = match (fled.as_slice(), &run.name) {
+        ([name], _) | (_, Some(name)) => name.bold().to_string(),
+        _ => "The party".bold().to_string(),
+    };
+    let mut story = match curr_status {
+        MetaStatus::HelpWanted | MetaStatus::OutsideTheDungeon => format!(
             "{} decides to go to the tavern for a drink instead!", 
-            character.bold()
+            who
         ),
         MetaStatus::InTheDungeon => format!(
             "{} exits the dungeon!", 
-            character.bold()
+            who
         ),
         MetaStatus::InCombat => format!(
             "{} escapes from battle and runs out the dungeon!", 
-            character.bold()
+            who
         ),
     };
 
+    if matches!(curr_status, MetaStatus::InTheDungeon | MetaStatus::InCombat) {
+        for name in &fled {
+            if let Some(bounty) = pay_bounty(name)? {
+                story = format!("{} {}", story, bounty);
+            }
+        }
+    }
+
     Ok(
-        ret
+        Escape { fled, from: curr_status, status: meta.status(), story }
     )
 }
 
-pub fn process_run() {
+pub fn process_run(run: &Run) -> Result<(), ClrpgError> {
     
-    match run_away() {
-        Ok(s) => {
-            infoln!("{}", "Waiting character succeeded.");
-            println!("{}", s);
+    match run_away(run) {
+        Ok(escape) => {
+            infoln!("{}", "Running away succeeded.");
+            if is_json() {
+                print_json(&escape);
+            } else {
+                println!("{}", escape.story);
+            }
+            Ok(())
         }
         Err(err) => {
-            errln!("{}", "Waiting character failed.");
-            println!("{}", err.red());
+            errln!("{}", "Running away failed.");
+            Err(err)
         }
     }
 }
\ No newline at end of file
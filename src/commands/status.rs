@@ -1,50 +1,111 @@
 use crate::{errln, infoln};
-use crate::utils::common::*;
+use crate::utils::{common::*, error::ClrpgError, dungeon::{require_floor, RoomKind, FINAL_FLOOR}, ledger::require_treasury, print::*};
 
 use colored::Colorize;
+use serde::Serialize;
 
-fn print_status() -> Result<(), String> {
+#[derive(Serialize)]
+struct Status {
+    status: MetaStatus,
+    /// The status as shown to the player
+    #[serde(skip)]
+    headline: String,
+    /// Deepest floor the dungeon has opened up
+    deepest: u32,
+    /// Deepest floor any adventurer has set foot on, 0 if none has yet
+    reached: u32,
+    cleared: bool,
+    position: Option<Position>,
+    room: Option<RoomKind>,
+    /// Members of the party, in the order they joined
+    party: Vec<CharacterObj>,
+    treasury: u32,
+}
+
+fn read_status() -> Result<Status, ClrpgError> {
+    require_root()?;
     let meta = require_meta()?;
     
-    let char_opt = match &meta.current {
-        Some(character) => {
-            Some(require_character(&character)?)
+    let party = meta.party().iter()
+        .map(|name| require_character(name))
+        .collect::<Result<Vec<CharacterObj>, ClrpgError>>()?;
+
+    let room = match &meta.position {
+        Some(position) => {
+            let floor = require_floor(&meta, position.floor)?;
+            Some(floor.room(position.room).kind)
         },
         None => None,
     };
 
-    infoln!("{}", "Status succeeded.");
-    // infoln!("{:?}", &meta);
-    // println!("Seed: {}", meta.seed);
-    println!("{}", meta.status);
+    let treasury = require_treasury()?.gold();
+    let reached = load_characters().iter().map(|c| c.get_deepest()).max().unwrap_or(0);
 
-    println!();
+    Ok(
+        Status {
+            status: meta.status(),
+            headline: meta.to_string(),
+            deepest: meta.deepest,
+            reached,
+            cleared: meta.cleared,
+            position: meta.position.clone(),
+            room,
+            party,
+            treasury,
+        }
+    )
+}
 
-    match char_opt {
-        Some(character) => {
-            println!("Current Adventurer");
-            println!("{:>3}", character);
-        },
-        None => {
-            println!("{}", "No waiting adventurer".red());
-            println!(
-                "   (use \"{} {}\" to wait an adventurer)", 
-                "clrpg".yellow(), "character wait <NAME>".black()
-            );
-        },
+fn print_status(status: &Status) {
+    println!("{}", status.headline);
+
+    if let (Some(position), Some(room)) = (&status.position, &status.room) {
+        println!("{} ({})", position, room);
     }
 
+    println!();
 
-    Ok(())
+    if status.party.is_empty() {
+        println!("{}", "No waiting adventurer".red());
+        println!(
+            "   (use \"{} {}\" to wait an adventurer)", 
+            "clrpg".yellow(), "character wait <NAME>".black()
+        );
+    } else {
+        println!("Party ({}/{})", status.party.len(), PARTY_SIZE);
+    }
+    for member in &status.party {
+        println!("   {}   {} LEVEL {}", member.get_life_string(), member.get_name().bold(), member.get_level());
+        println!("{:>3}", member.stats().health());
+        println!("   PURSE:  {} gold", member.inventory().gold().to_string().yellow());
+        match member.get_deepest() {
+            0 => println!("   DEPTH:  {}", "never entered".black()),
+            depth => println!("   DEPTH:  floor {}", depth),
+        }
+    }
+
+    println!("\n{} {} gold", "Treasury".bold(), status.treasury.to_string().yellow());
+    println!(
+        "{} {} of {} {}",
+        "Deepest floor reached".bold(), status.reached, FINAL_FLOOR,
+        format!("({} open)", status.deepest).black()
+    );
 }
 
-pub fn process_status() {
-    match print_status() {
-        Ok(_) => {
+pub fn process_status() -> Result<(), ClrpgError> {
+    match read_status() {
+        Ok(status) => {
+            infoln!("{}", "Status succeeded.");
+            if is_json() {
+                print_json(&status);
+            } else {
+                print_status(&status);
+            }
+            Ok(())
         },
         Err(err) => {
             errln!("{}", "Status failed.");
-            println!("{}", err.red());
+            Err(err)
         }
     }
-}
\ No newline at end of file
+}
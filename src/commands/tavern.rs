@@ -0,0 +1,309 @@
+use crate::{errln, infoln};
+use crate::utils::{common::*, error::ClrpgError, classes::load_classes, dungeon::*, items::*, ledger::*, print::*};
+
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use rand::prelude::*;
+use serde::Serialize;
+
+/// Price of a night in a tavern room
+pub const ROOM_PRICE: u32 = 10;
+
+#[derive(Args)]
+pub struct Rest {
+    /// Character resting.
+    #[clap(value_parser)]
+    name: String,
+
+    /// Rent a room instead of dozing off by the hearth.
+    #[clap(long, action)]
+    room: bool,
+}
+
+#[derive(Args)]
+pub struct Buy {
+    /// Item to buy.
+    #[clap(value_parser)]
+    item: String,
+
+    /// Character buying the item; the party leader if not given.
+    #[clap(short, long, value_parser)]
+    character: Option<String>,
+}
+
+#[derive(Args)]
+pub struct Sell {
+    /// Item to sell.
+    #[clap(value_parser)]
+    item: String,
+
+    /// Character selling the item; the party leader if not given.
+    #[clap(short, long, value_parser)]
+    character: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Subcommands {
+    /// Doze off by the hearth for free, waking up with half of the health
+    /// back when next waited, or rent a room to recover all of it at once.
+    Rest(Rest),
+
+    /// List the wares on sale.
+    Shop,
+
+    /// Buy an item, in the tavern or from the merchant of a shop room.
+    Buy(Buy),
+
+    /// Sell a carried item, in the tavern or to the merchant of a shop room.
+    Sell(Sell),
+
+    /// Hire a new adventurer of a random class.
+    Hire,
+
+    /// View the adventurers sitting in the tavern.
+    View,
+}
+
+#[derive(Args)]
+pub struct Tavern {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+/// Statuses in which the party is still around the tavern
+const IN_TOWN: [MetaStatus; 2] = [MetaStatus::HelpWanted, MetaStatus::OutsideTheDungeon];
+
+/// Whether the party stands in a shop room, where the merchant trades the
+/// same wares as the tavern
+fn at_shop(meta: &Meta) -> Result<bool, ClrpgError> {
+    match &meta.position {
+        Some(position) if meta.status() == MetaStatus::InTheDungeon => {
+            let floor = require_floor(meta, position.floor)?;
+            Ok(floor.room(position.room).kind == RoomKind::Shop)
+        },
+        _ => Ok(false),
+    }
+}
+
+/// Reads the named character, or the party leader if no name is given, as
+/// long as they are alive and not inside the dungeon. Trading is also
+/// allowed with the merchant of a shop room.
+fn require_patron(name: Option<&str>, trading: bool) -> Result<CharacterObj, ClrpgError> {
+    let (character, meta) = require_owner(name)?;
+    if meta.in_party(character.get_name()) && !(trading && at_shop(&meta)?) {
+        meta.require_status(&IN_TOWN, Transition::Wait)?;
+    }
+
+    if !character.is_alive() {
+        return Err(ClrpgError::DeadCharacter { name: character.get_name().to_string() });
+    }
+    Ok(character)
+}
+
+/// Takes the price out of the character's purse and into the treasury. The
+/// treasury is written along with the character.
+fn pay(character: &mut CharacterObj, cost: u32, reason: Reason) -> Result<Treasury, ClrpgError> {
+    let mut treasury = require_treasury()?;
+    spend(character, cost, reason)?;
+    treasury.deposit(cost, character.get_name(), reason);
+    Ok(treasury)
+}
+
+/// Lets the character rest. The hearth is free, but the rest lasts until
+/// the character is next waited, so members of the party cannot take it.
+fn rest(rest: &Rest) -> Result<String, ClrpgError> {
+    let mut character = require_patron(Some(&rest.name), false)?;
+    if !rest.room {
+        if require_meta()?.in_party(&rest.name) {
+            return Err(ClrpgError::CharacterBusy { name: rest.name.clone() });
+        }
+        character.rest();
+        write_character(&character)?;
+        return Ok(format!("{} settles down by the hearth to rest.", rest.name.bold()));
+    }
+
+    let treasury = pay(&mut character, ROOM_PRICE, Reason::Lodging)?;
+
+    let healed = character.lodge();
+    write_character(&character)?;
+    write_treasury(&treasury)?;
+
+    Ok(
+        format!(
+            "{} pays {} gold for a room and sleeps soundly, recovering {} health.",
+            rest.name.bold(), ROOM_PRICE, healed
+        )
+    )
+}
+
+fn render_shop(stock: &[Item]) -> String {
+    let mut s = format!("   {}\n", "Wares".bold());
+    for item in stock {
+        s.push_str(&format!(
+            "   {:>4} gold  {:<22} {} {}\n",
+            item.value.to_string().yellow(), item, item.summary(),
+            format!("({})", item.kind).black()
+        ));
+    }
+    s
+}
+
+fn buy(buy: &Buy) -> Result<String, ClrpgError> {
+    let mut character = require_patron(buy.character.as_deref(), true)?;
+    let name = character.get_name().to_string();
+
+    let item = tavern_stock().into_iter()
+        .find(|i| i.name.eq_ignore_ascii_case(&buy.item))
+        .ok_or_else(|| ClrpgError::InvalidChoice(format!("The tavern does not sell {}.", buy.item)))?;
+    let treasury = pay(&mut character, item.value, Reason::Purchase)?;
+
+    let story = format!("{} buys the {} for {} gold.", name.bold(), item, item.value);
+    character.inventory_mut().add(item);
+    write_character(&character)?;
+    write_treasury(&treasury)?;
+
+    Ok(story)
+}
+
+fn sell(sell: &Sell) -> Result<String, ClrpgError> {
+    let mut character = require_patron(sell.character.as_deref(), true)?;
+    let name = character.get_name().to_string();
+
+    let item = character.inventory_mut().take(&sell.item)
+        .ok_or_else(|| ClrpgError::MissingItem { name: name.clone(), item: sell.item.clone() })?;
+    let price = item.sell_price();
+
+    // the treasury buys the item back
+    let mut treasury = require_treasury()?;
+    treasury.withdraw(price, &name, Reason::Sale)?;
+    earn(&mut character, price, Reason::Sale);
+    write_character(&character)?;
+    write_treasury(&treasury)?;
+
+    Ok(format!("{} sells the {} for {} gold.", name.bold(), item, price))
+}
+
+fn hire() -> Result<String, ClrpgError> {
+    require_root()?;
+    require_char()?;
+
+    let mut state = RandomState::single_use()?;
+    let class = load_classes()
+        .choose(&mut state.rng)
+        .cloned()
+        .expect("there are always built-in classes");
+
+    let name = state.generate_name()?;
+    let id = state.generate_id();
+    let stats = class.roll_stats(&mut state);
+    let inventory = Inventory::starting_kit(&mut state);
+
+    let mut treasury = require_treasury()?;
+    treasury.withdraw(HIRING_FEE, &name, Reason::Hiring)?;
+    create_character(id, name.clone(), Some(&class), stats, Creation::Random, inventory)?;
+    write_treasury(&treasury)?;
+    infoln!("Created {}", name.yellow().bold());
+
+    Ok(
+        format!(
+            "{} the {} answers the notice on the tavern wall and pulls up a chair, for a fee of {} gold from the treasury.",
+            name.bold(), class.name, HIRING_FEE
+        )
+    )
+}
+
+/// An adventurer sitting in the tavern
+#[derive(Serialize)]
+struct Patron {
+    name: String,
+    level: u32,
+    class: Option<String>,
+    status: CharacterStatus,
+    gold: u32,
+}
+
+fn view() -> Result<Vec<Patron>, ClrpgError> {
+    require_root()?;
+    require_char()?;
+    let meta = require_meta()?;
+
+    // the party only counts while it has not left town
+    let away = meta.require_status(&IN_TOWN, Transition::Wait).is_err();
+
+    let mut patrons: Vec<Patron> = load_characters().into_iter()
+        .filter(|c| c.is_alive() && !(away && meta.in_party(c.get_name())))
+        .map(|c| Patron {
+            name: c.get_name().to_string(),
+            level: c.get_level(),
+            class: c.get_class().map(str::to_string),
+            status: c.get_status(),
+            gold: c.inventory().gold(),
+        })
+        .collect();
+    patrons.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(patrons)
+}
+
+fn render_patrons(patrons: &[Patron]) -> String {
+    if patrons.is_empty() {
+        return format!("{}", "The tavern is empty, save for the barkeep.".black());
+    }
+
+    patrons.iter()
+        .map(|p| format!(
+            "   {:<20} LEVEL {:<3} {:<8} {:9} {} gold",
+            p.name.yellow().bold(), p.level, p.class.as_deref().unwrap_or("-"),
+            p.status, p.gold.to_string().yellow()
+        ))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Prints the outcome of a tavern command that only tells a story
+fn report(result: Result<String, ClrpgError>, action: &str) -> Result<(), ClrpgError> {
+    match result {
+        Ok(s) => {
+            infoln!("{} succeeded.", action);
+            print_message(&s);
+            Ok(())
+        },
+        Err(err) => {
+            errln!("{} failed.", action);
+            Err(err)
+        }
+    }
+}
+
+pub fn process_tavern(tavern: &Tavern) -> Result<(), ClrpgError> {
+    match &tavern.command {
+        Subcommands::Rest(r) => report(rest(r), "Resting"),
+        Subcommands::Buy(b) => report(buy(b), "Buying"),
+        Subcommands::Sell(s) => report(sell(s), "Selling"),
+        Subcommands::Hire => report(hire(), "Hiring"),
+        Subcommands::Shop => {
+            let stock = tavern_stock();
+            if is_json() {
+                print_json(&stock);
+            } else {
+                println!("{}", render_shop(&stock));
+            }
+            Ok(())
+        },
+        Subcommands::View => match view() {
+            Ok(patrons) => {
+                infoln!("{}", "Viewing the tavern succeeded.");
+                if is_json() {
+                    print_json(&patrons);
+                } else {
+                    println!("{}", render_patrons(&patrons));
+                }
+                Ok(())
+            },
+            Err(err) => {
+                errln!("{}", "Viewing the tavern failed.");
+                Err(err)
+            }
+        },
+    }
+}
@@ -1,10 +1,8 @@
-#![feature(format_args_nl)]
-#![feature(print_internals)]
-
 mod commands;
 mod utils;
 
-use clap::{AppSettings, Parser};
+use clap::{AppSettings, ArgAction, Parser};
+use utils::print::{is_json, set_format, set_verbosity, OutputFormat};
 
 #[derive(Parser)]
 #[clap(author)]
@@ -13,22 +11,47 @@ use clap::{AppSettings, Parser};
 #[clap(setting(AppSettings::SubcommandRequiredElseHelp))]
 #[clap(global_setting(AppSettings::DeriveDisplayOrder))]
 #[clap(global_setting(AppSettings::DisableHelpSubcommand))]
+#[clap(after_help = EXIT_CODES)]
 struct Cli {
     #[clap(subcommand)]
     command: commands::Commands,
     
-    /// Verbose debug information
+    /// Show info logs; repeat for debug traces
+    #[clap(short, long, action = ArgAction::Count, global = true)]
+    verbose: u8,
+
+    /// Skip the narration
     #[clap(short, long, action, global = true)]
-    verbose: bool
+    quiet: bool,
 
-}
+    /// Output format
+    #[clap(long, arg_enum, value_name = "FORMAT", default_value = "text", global = true)]
+    format: OutputFormat,
 
+}
 
+const EXIT_CODES: &str = "EXIT CODES:
+    0    Success
+    1    Unexpected or I/O failure
+    2    Invalid usage
+    3    There is no dungeon
+    4    The dungeon is corrupted
+    5    Unknown character or item
+    6    Command not allowed in the current state
+    7    The character is dead
+    8    Action refused (already exists, no passage, ...)";
 
 fn main() {
     let mut cli = Cli::parse();
 
-    // use crate::utils::print::VERBOSE;
-    // VERBOSE.with(|b| *b.borrow_mut() = cli.verbose);
-    commands::process_command(&mut cli.command);
+    set_verbosity(cli.verbose, cli.quiet);
+    set_format(cli.format);
+    if let Err(err) = commands::process_command(&mut cli.command) {
+        if is_json() {
+            eprintln!("{}", err.to_json());
+        } else {
+            eprintln!("{}", err.render());
+        }
+        std::process::exit(err.exit_code());
+    }
 }
\ No newline at end of file
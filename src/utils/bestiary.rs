@@ -0,0 +1,407 @@
+use colored::Colorize;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{common::*, items::{find_item, Bonus, Loot}};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum MonsterTier {
+    Minion,
+    Common,
+    Elite,
+    Champion,
+    /// Master of a floor, never rolled for a regular encounter
+    Boss,
+}
+
+impl MonsterTier {
+    /// Relative odds of the tier being rolled for an encounter
+    fn weight(&self) -> u32 {
+        match *self {
+            MonsterTier::Minion => 50,
+            MonsterTier::Common => 35,
+            MonsterTier::Elite => 12,
+            MonsterTier::Champion => 3,
+            MonsterTier::Boss => 0,
+        }
+    }
+
+    /// Experience earned for defeating a monster of the tier
+    pub fn xp(&self) -> u32 {
+        match *self {
+            MonsterTier::Minion => 20,
+            MonsterTier::Common => 40,
+            MonsterTier::Elite => 80,
+            MonsterTier::Champion => 150,
+            MonsterTier::Boss => 300,
+        }
+    }
+
+    /// Range of gold carried by a monster of the tier
+    fn gold(&self) -> (u32, u32) {
+        match *self {
+            MonsterTier::Minion => (1, 5),
+            MonsterTier::Common => (3, 10),
+            MonsterTier::Elite => (10, 25),
+            MonsterTier::Champion => (25, 60),
+            MonsterTier::Boss => (60, 120),
+        }
+    }
+}
+
+impl std::fmt::Display for MonsterTier {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            MonsterTier::Minion => write!(f, "{}", "MINION".black()),
+            MonsterTier::Common => write!(f, "{}", "COMMON".white()),
+            MonsterTier::Elite => write!(f, "{}", "ELITE".yellow()),
+            MonsterTier::Champion => write!(f, "{}", "CHAMPION".red().bold()),
+            MonsterTier::Boss => write!(f, "{}", "BOSS".magenta().bold()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum AbilityEffect {
+    /// Regular strike with bonus physical damage
+    Strike(i32),
+    /// Magic damage that ignores faith and heals the user by the damage dealt
+    Drain(i32),
+    /// Magic damage that ignores faith
+    Blast(i32),
+    /// Heals the user
+    Heal(i32),
+    /// Faith-scaled heal of the user, going past the maximum as temporary health
+    Pray(i32),
+    /// Gives the user temporary health
+    Shield(i32),
+    /// Puts a status effect on the opponent, e.g. poison or a debuff
+    Afflict { stat: Stat, amount: i32, turns: i32 },
+    /// Puts a status effect on the user, e.g. regeneration or a buff
+    Bless { stat: Stat, amount: i32, turns: i32 },
+    /// Makes the opponent skip their next turns
+    Stun(i32),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Ability {
+    pub name: String,
+    /// Chance of the ability being used instead of a regular strike
+    pub chance: f64,
+    pub effect: AbilityEffect,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LootEntry {
+    pub item: String,
+    pub weight: u32,
+}
+
+/// Definition of a monster as read from `res/monsters.json`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct MonsterDef {
+    name: String,
+    tier: MonsterTier,
+    health: (i32, i32),
+    power: (i32, i32),
+    block: (i32, i32),
+    magic: (i32, i32),
+    faith: (i32, i32),
+    speed: (i32, i32),
+    abilities: Vec<Ability>,
+    loot_chance: f64,
+    loot: Vec<LootEntry>,
+}
+
+impl MonsterDef {
+    /// Rolls the stats of the monster, each scaled by the given function
+    fn roll_stats(&self, rng: &mut Prng, scale: impl Fn(i32) -> i32) -> CharacterStats {
+        let mut roll = |(min, max): (i32, i32)| scale(rng.gen_range(min..=max));
+        CharacterStats::new(
+            roll(self.health),
+            roll(self.power),
+            roll(self.block),
+            roll(self.magic),
+            roll(self.faith),
+            roll(self.speed)
+        )
+    }
+}
+
+/// A stage of a boss fight, entered once the boss is worn down far enough
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Phase {
+    /// Health, in percent of the maximum, at which the phase begins
+    pub threshold: i32,
+    pub narration: String,
+    /// Abilities replacing those of the previous phase
+    pub abilities: Vec<Ability>,
+    /// Lasting stat changes taken on for the rest of the fight
+    #[serde(default)]
+    pub bonuses: Vec<Bonus>,
+}
+
+/// What sets the master of a floor apart from a regular monster
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BossScript {
+    pub intro: String,
+    pub defeat: String,
+    phases: Vec<Phase>,
+    /// Number of phases entered so far
+    #[serde(default)]
+    entered: usize,
+}
+
+impl BossScript {
+    /// The phase the fight is in, counting the opening one as the first
+    pub fn phase(&self) -> usize {
+        self.entered + 1
+    }
+
+    pub fn phase_count(&self) -> usize {
+        self.phases.len() + 1
+    }
+}
+
+/// Definition of a boss as read from `res/bosses.json`
+#[derive(Debug, Clone, Deserialize)]
+struct BossDef {
+    #[serde(flatten)]
+    monster: MonsterDef,
+    title: String,
+    intro: String,
+    defeat: String,
+    phases: Vec<Phase>,
+}
+
+fn bestiary() -> Vec<MonsterDef> {
+    serde_json::from_str(include_str!("../../res/monsters.json"))
+        .expect("res/monsters.json is malformed")
+}
+
+fn bosses() -> Vec<BossDef> {
+    serde_json::from_str(include_str!("../../res/bosses.json"))
+        .expect("res/bosses.json is malformed")
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Monster {
+    name: String,
+    kind: String,
+    tier: MonsterTier,
+    stats: CharacterStats,
+    abilities: Vec<Ability>,
+    loot_chance: f64,
+    loot: Vec<LootEntry>,
+    #[serde(default)]
+    boss: Option<BossScript>,
+}
+
+impl Monster {
+    /// Rolls a tier, then a monster of that tier, then its stats
+    pub fn from_rng(rng: &mut RandomState) -> Monster {
+        let defs = bestiary();
+
+        let tiers = [
+            MonsterTier::Minion,
+            MonsterTier::Common,
+            MonsterTier::Elite,
+            MonsterTier::Champion
+        ];
+        let tier = *tiers.choose_weighted(&mut rng.rng, |t| t.weight()).unwrap();
+
+        let def = defs.iter()
+            .filter(|d| d.tier == tier)
+            .choose_stable(&mut rng.rng)
+            .expect("res/monsters.json has an empty tier");
+
+        let adjective = include_str!("../../res/adjectives.txt").lines()
+            .choose_stable(&mut rng.rng)
+            .unwrap();
+
+        Monster {
+            name: format!("{} {}", adjective, def.name),
+            kind: def.name.clone(),
+            tier: def.tier,
+            stats: def.roll_stats(&mut rng.rng, |stat| stat),
+            abilities: def.abilities.clone(),
+            loot_chance: def.loot_chance,
+            loot: def.loot.clone(),
+            boss: None,
+        }
+    }
+
+    /// The master of the floor at the given depth. Like the floor itself,
+    /// the boss is picked and rolled purely from the dungeon seed and the
+    /// depth, and grows a quarter stronger with every floor down.
+    pub fn floor_boss(seed: u64, depth: u32) -> Monster {
+        let mut defs = bosses();
+        // every floor gets a different boss until the pool runs out
+        defs.shuffle(&mut Prng::seed_from_u64(seed));
+        let def = defs.swap_remove((depth.max(1) as usize - 1) % defs.len());
+
+        let mut rng = Prng::seed_from_u64(
+            seed.wrapping_sub(depth as u64).wrapping_mul(0xD1B5_4A32_D192_ED03)
+        );
+        let growth = depth.max(1) as i32 - 1;
+
+        Monster {
+            name: def.title,
+            kind: def.monster.name.clone(),
+            tier: def.monster.tier,
+            stats: def.monster.roll_stats(&mut rng, |stat| stat + stat * growth / 4),
+            abilities: def.monster.abilities,
+            loot_chance: def.monster.loot_chance,
+            loot: def.monster.loot,
+            boss: Some(BossScript { intro: def.intro, defeat: def.defeat, phases: def.phases, entered: 0 }),
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_kind(&self) -> &str {
+        &self.kind
+    }
+
+    pub fn get_tier(&self) -> MonsterTier {
+        self.tier
+    }
+
+    pub fn stats(&self) -> &CharacterStats {
+        &self.stats
+    }
+
+    pub fn stats_mut(&mut self) -> &mut CharacterStats {
+        &mut self.stats
+    }
+
+    pub fn is_alive(&self) -> bool {
+        !self.stats.health().is_depleted()
+    }
+
+    pub fn boss(&self) -> Option<&BossScript> {
+        self.boss.as_ref()
+    }
+
+    /// Moves a boss on to its next phase once its health drops to the
+    /// phase's threshold. Returns the narration of the phase entered.
+    pub fn advance_phase(&mut self) -> Option<String> {
+        let script = self.boss.as_mut()?;
+        let phase = script.phases.get(script.entered)?;
+        if self.stats.health().percent() > phase.threshold {
+            return None;
+        }
+
+        script.entered += 1;
+        self.abilities = phase.abilities.clone();
+        for bonus in &phase.bonuses {
+            self.stats.apply(bonus.stat, Modifier {
+                name: format!("Phase {}", script.entered + 1), amount: bonus.amount, turns: Modifier::LASTING
+            });
+        }
+        Some(phase.narration.clone())
+    }
+
+    /// Picks the first ability whose chance succeeds, if any
+    pub fn roll_ability(&self, rng: &mut RandomState) -> Option<&Ability> {
+        self.abilities.iter()
+            .find(|a| rng.rng.gen_bool(a.chance.clamp(0.0, 1.0)))
+    }
+
+    /// Rolls the gold the monster carries and its loot table
+    pub fn roll_loot(&self, rng: &mut RandomState) -> Loot {
+        let (min, max) = self.tier.gold();
+        let mut loot = Loot { items: vec![], gold: rng.rng.gen_range(min..=max) };
+
+        if self.loot.is_empty() || !rng.rng.gen_bool(self.loot_chance.clamp(0.0, 1.0)) {
+            return loot;
+        }
+
+        let entry = self.loot.choose_weighted(&mut rng.rng, |l| l.weight).ok();
+        if let Some(item) = entry.and_then(|l| find_item(&l.item)) {
+            loot.items.push(item);
+        }
+        loot
+    }
+}
+
+/// Monster with the given stats, no abilities and no loot, for tests
+#[cfg(test)]
+pub fn test_monster(name: &str, stats: CharacterStats) -> Monster {
+    serde_json::from_value(serde_json::json!({
+        "name": name,
+        "kind": name,
+        "tier": "Minion",
+        "stats": stats,
+        "abilities": [],
+        "loot_chance": 0.0,
+        "loot": [],
+    })).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json<T: Serialize>(value: &T) -> String {
+        serde_json::to_string(value).unwrap()
+    }
+
+    #[test]
+    fn monsters_are_rolled_from_the_seed() {
+        for seed in 0..20 {
+            let a = Monster::from_rng(&mut RandomState::seeded(seed));
+            let b = Monster::from_rng(&mut RandomState::seeded(seed));
+            assert_eq!(json(&a), json(&b));
+        }
+
+        let rolled = (0..20)
+            .map(|seed| json(&Monster::from_rng(&mut RandomState::seeded(seed))))
+            .collect::<std::collections::HashSet<String>>();
+        assert!(rolled.len() > 1);
+    }
+
+    #[test]
+    fn monsters_are_rolled_within_their_definition() {
+        let mut rng = RandomState::seeded(7);
+        for _ in 0..50 {
+            let monster = Monster::from_rng(&mut rng);
+            let def = bestiary().into_iter().find(|d| d.name == monster.kind).unwrap();
+            assert_eq!(monster.tier, def.tier);
+
+            let stats = monster.stats();
+            for (value, (min, max)) in [
+                (stats.power(), def.power), (stats.block(), def.block), (stats.magic(), def.magic),
+                (stats.faith(), def.faith), (stats.speed(), def.speed),
+            ] {
+                assert!((min..=max).contains(&value), "{} out of {:?}", value, (min, max));
+            }
+        }
+    }
+
+    #[test]
+    fn loot_is_rolled_from_the_seed() {
+        let monster = Monster::from_rng(&mut RandomState::seeded(7));
+        for seed in 0..20 {
+            assert_eq!(
+                json(&monster.roll_loot(&mut RandomState::seeded(seed))),
+                json(&monster.roll_loot(&mut RandomState::seeded(seed)))
+            );
+        }
+
+        let (min, max) = monster.tier.gold();
+        for seed in 0..50 {
+            let loot = monster.roll_loot(&mut RandomState::seeded(seed));
+            assert!((min..=max).contains(&loot.gold));
+            assert!(loot.items.iter().all(|item| monster.loot.iter().any(|l| l.item == item.name)));
+        }
+    }
+
+    #[test]
+    fn monsters_without_loot_drop_only_gold() {
+        let monster = test_monster("Test Rat", CharacterStats::new(10, 1, 1, 1, 1, 1));
+        assert!((0..20).all(|seed| monster.roll_loot(&mut RandomState::seeded(seed)).items.is_empty()));
+    }
+}
@@ -0,0 +1,98 @@
+use std::fs::read_to_string;
+
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::errln;
+use super::{common::*, bestiary::Ability, error::ClrpgError};
+
+/// Archetype picked at character creation, as read from `res/classes.json`
+/// or from the custom classes file of the dungeon
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CharacterClass {
+    pub name: String,
+    pub description: String,
+    health: i32,
+    power: (i32, i32),
+    block: (i32, i32),
+    magic: (i32, i32),
+    faith: (i32, i32),
+    speed: (i32, i32),
+    pub abilities: Vec<Ability>,
+}
+
+impl CharacterClass {
+    pub fn health(&self) -> i32 {
+        self.health
+    }
+
+    /// Rolls starting stats within the ranges of the class
+    pub fn roll_stats(&self, rng: &mut RandomState) -> CharacterStats {
+        let mut roll = |(min, max): (i32, i32)| rng.rng.gen_range(min..=max);
+
+        CharacterStats::new(
+            self.health,
+            roll(self.power),
+            roll(self.block),
+            roll(self.magic),
+            roll(self.faith),
+            roll(self.speed)
+        )
+    }
+
+    /// Describes what is wrong with the class, if anything: stat ranges have
+    /// to be ordered and start at `STAT_MIN`, and health has to be positive
+    fn problem(&self) -> Option<String> {
+        if self.health < 1 {
+            return Some(format!("health {} is not positive", self.health));
+        }
+
+        let ranges = [
+            ("power", self.power), ("block", self.block), ("magic", self.magic),
+            ("faith", self.faith), ("speed", self.speed),
+        ];
+        ranges.iter().find_map(|(stat, (min, max))| {
+            if min > max {
+                Some(format!("{} range [{}, {}] is empty", stat, min, max))
+            } else if *min < STAT_MIN {
+                Some(format!("{} range [{}, {}] starts below {}", stat, min, max, STAT_MIN))
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// Built-in classes, followed by the custom ones. A custom class replaces
+/// the built-in class of the same name.
+pub fn load_classes() -> Vec<CharacterClass> {
+    let mut classes: Vec<CharacterClass> = serde_json::from_str(include_str!("../../res/classes.json"))
+        .expect("res/classes.json is malformed");
+
+    // a broken custom file should not keep the built-in classes from working
+    let custom: Vec<CharacterClass> = match read_to_string(CLASSES_FILE_NAME) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|err| {
+            errln!("{} is malformed: {}", CLASSES_FILE_NAME, err);
+            vec![]
+        }),
+        Err(_) => vec![],
+    };
+
+    for class in custom {
+        if let Some(problem) = class.problem() {
+            errln!("{} has an invalid class {}: {}", CLASSES_FILE_NAME, class.name, problem);
+            continue;
+        }
+        classes.retain(|c| !c.name.eq_ignore_ascii_case(&class.name));
+        classes.push(class);
+    }
+    classes
+}
+
+/// Looks the class up by name, ignoring case
+pub fn require_class(name: &str) -> Result<CharacterClass, ClrpgError> {
+    load_classes()
+        .into_iter()
+        .find(|c| c.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| ClrpgError::UnknownClass { name: name.to_string() })
+}
@@ -0,0 +1,513 @@
+use std::cmp::Reverse;
+
+use colored::Colorize;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{common::*, bestiary::*, error::ClrpgError};
+use crate::debugln;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Attack {
+    pub attacker: String,
+    pub defender: String,
+    pub ability: Option<String>,
+    pub physical: i32,
+    pub magical: i32,
+    pub healed: i32,
+    /// Temporary health gained by the attacker
+    #[serde(default)]
+    pub shielded: i32,
+    /// Status effect put on the attacker or the defender
+    #[serde(default)]
+    pub effect: Option<AppliedEffect>,
+    /// Turns the defender is stunned for
+    #[serde(default)]
+    pub stun: i32,
+    /// Whether the attacker lost the turn to a stun
+    #[serde(default)]
+    pub skipped: bool,
+}
+
+impl Attack {
+    fn new(attacker: &str, defender: &str) -> Self {
+        Attack {
+            attacker: attacker.to_string(),
+            defender: defender.to_string(),
+            ability: None,
+            physical: 0,
+            magical: 0,
+            healed: 0,
+            shielded: 0,
+            effect: None,
+            stun: 0,
+            skipped: false,
+        }
+    }
+
+    fn stunned(attacker: &str, defender: &str) -> Self {
+        Attack { skipped: true, ..Attack::new(attacker, defender) }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AppliedEffect {
+    pub target: String,
+    pub stat: Stat,
+    pub amount: i32,
+    pub turns: i32,
+}
+
+impl std::fmt::Display for AppliedEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let per_turn = if self.stat == Stat::Health { " per turn" } else { "" };
+        write!(f, "{} gets {:+} {}{} for {} turns!",
+            self.target, self.amount, self.stat, per_turn, self.turns
+        )
+    }
+}
+
+impl std::fmt::Display for Attack {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(ability) = &self.ability {
+            write!(f, "{} uses {}! ", self.attacker, ability)?;
+        }
+
+        if self.skipped {
+            write!(f, "{} is stunned and cannot act!", self.attacker)
+        } else if self.stun > 0 {
+            write!(f, "{} is stunned!", self.defender)
+        } else if let Some(effect) = &self.effect {
+            write!(f, "{}", effect)
+        } else if self.shielded > 0 && self.healed > 0 {
+            write!(f, "{} recovers {} health and gains {} temporary health!",
+                self.attacker, self.healed, self.shielded
+            )
+        } else if self.shielded > 0 {
+            write!(f, "{} gains {} temporary health!", self.attacker, self.shielded)
+        } else if self.physical == 0 && self.magical == 0 {
+            write!(f, "{} recovers {} health!", self.attacker, self.healed)
+        } else if self.physical == 0 && self.healed == 0 {
+            write!(f, "{} blasts {} for {} magic damage!",
+                self.attacker, self.defender, self.magical
+            )
+        } else if self.physical == 0 {
+            write!(f, "{} drains {} health from {}!",
+                self.attacker, self.magical, self.defender
+            )
+        } else if self.magical > 0 {
+            write!(f, "{} strikes {} for {} damage and blasts them with {} magic damage!",
+                self.attacker, self.defender, self.physical, self.magical
+            )
+        } else {
+            write!(f, "{} strikes {} for {} damage!",
+                self.attacker, self.defender, self.physical
+            )
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CombatRound {
+    pub round: u32,
+    pub attacks: Vec<Attack>,
+    /// Status effects ticking at the end of the round
+    #[serde(default)]
+    pub ticks: Vec<Tick>,
+    /// Narration of the boss phases entered during the round
+    #[serde(default)]
+    pub phases: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum CombatOutcome {
+    Ongoing,
+    Victory,
+    Defeat,
+}
+
+/// Who acts in a turn of the round
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combatant {
+    /// Position of the member in the party
+    Member(usize),
+    Enemy,
+}
+
+/// An encounter between the party and a monster, persisted to
+/// `.dungeon/.combat` between rounds
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Combat {
+    enemy: Monster,
+    rounds: Vec<CombatRound>,
+}
+
+impl Combat {
+    pub fn new(enemy: Monster) -> Self {
+        Combat { enemy, rounds: vec![] }
+    }
+
+    pub fn enemy(&self) -> &Monster {
+        &self.enemy
+    }
+
+    pub fn round(&self) -> u32 {
+        self.rounds.len() as u32
+    }
+
+    /// Resolves a single round of combat between the party and the enemy.
+    ///
+    /// Everyone still standing acts once, the fastest first (ties are
+    /// settled by a roll). Exhausted members always act last, stunned
+    /// combatants skip their turn, and the enemy strikes at a random member.
+    /// A boss worn down far enough enters its next phase at the end of the
+    /// round.
+    pub fn resolve_round(&mut self, party: &mut [CharacterObj], rng: &mut RandomState) -> CombatOutcome {
+        let mut initiative: Vec<(Combatant, bool, i32, u32)> = party.iter()
+            .enumerate()
+            .map(|(i, member)| (Combatant::Member(i), member.is_exhausted(), member.stats().speed(), rng.rng.gen()))
+            .collect();
+        initiative.push((Combatant::Enemy, false, self.enemy.stats().speed(), rng.rng.gen()));
+        initiative.sort_by_key(|&(_, exhausted, speed, roll)| (exhausted, Reverse(speed), roll));
+
+        let mut attacks = vec![];
+        let enemy_name = self.enemy.get_name().to_string();
+
+        for (combatant, ..) in initiative {
+            if !self.enemy.is_alive() || !party.iter().any(CharacterObj::is_alive) {
+                break;
+            }
+
+            let attack = match combatant {
+                Combatant::Member(i) if !party[i].is_alive() => continue,
+                Combatant::Member(i) => {
+                    let member = &mut party[i];
+                    let name = member.get_name().to_string();
+                    if member.stats_mut().skip_stunned_turn() {
+                        Attack::stunned(&name, &enemy_name)
+                    } else {
+                        let ability = member.roll_ability(rng).cloned();
+                        use_ability(
+                            &name, member.stats_mut(),
+                            &enemy_name, self.enemy.stats_mut(),
+                            ability, rng
+                        )
+                    }
+                },
+                Combatant::Enemy => {
+                    let target = (0..party.len())
+                        .filter(|&i| party[i].is_alive())
+                        .choose(&mut rng.rng)
+                        .expect("someone in the party is still standing");
+                    let member = &mut party[target];
+                    let name = member.get_name().to_string();
+                    if self.enemy.stats_mut().skip_stunned_turn() {
+                        Attack::stunned(&enemy_name, &name)
+                    } else {
+                        let ability = self.enemy.roll_ability(rng).cloned();
+                        use_ability(
+                            &enemy_name, self.enemy.stats_mut(),
+                            &name, member.stats_mut(),
+                            ability, rng
+                        )
+                    }
+                },
+            };
+            attacks.push(attack);
+        }
+
+        let mut ticks = vec![];
+        let mut phases = vec![];
+        if self.enemy.is_alive() && party.iter().any(CharacterObj::is_alive) {
+            for member in party.iter_mut().filter(|member| member.is_alive()) {
+                let name = member.get_name().to_string();
+                ticks.extend(member.stats_mut().tick(&name, true));
+            }
+            ticks.extend(self.enemy.stats_mut().tick(&enemy_name, true));
+
+            while let Some(narration) = self.enemy.advance_phase() {
+                debugln!("{} entered a new phase", enemy_name);
+                phases.push(narration);
+            }
+        }
+
+        self.rounds.push(CombatRound { round: self.round() + 1, attacks, ticks, phases });
+
+        let outcome = if !party.iter().any(CharacterObj::is_alive) {
+            CombatOutcome::Defeat
+        } else if !self.enemy.is_alive() {
+            CombatOutcome::Victory
+        } else {
+            CombatOutcome::Ongoing
+        };
+
+        for member in party.iter_mut() {
+            if outcome != CombatOutcome::Ongoing {
+                member.stats_mut().clear_stun();
+            }
+            member.tire();
+            member.refresh_status();
+        }
+
+        outcome
+    }
+
+    /// The member who last struck the enemy, who gets to claim the spoils
+    pub fn last_striker(&self) -> Option<&str> {
+        self.last_round()?
+            .attacks.iter()
+            .rev()
+            .find(|attack| attack.defender == self.enemy.get_name() && !attack.skipped)
+            .map(|attack| attack.attacker.as_str())
+    }
+
+    pub fn last_round(&self) -> Option<&CombatRound> {
+        self.rounds.last()
+    }
+
+    /// How the character met their end in the last round. Status effects
+    /// only tick once both combatants survived the attacks, so a lethal
+    /// tick takes precedence.
+    pub fn cause_of_death(&self, name: &str) -> String {
+        let round = match self.last_round() {
+            Some(round) => round,
+            None => return "Fell in combat".to_string(),
+        };
+
+        if let Some(tick) = round.ticks.iter().rev().find(|t| t.target == name && t.amount < 0) {
+            return format!("Succumbed to {}", tick.name);
+        }
+        match round.attacks.iter().rev().find(|a| a.defender == name).and_then(|a| a.ability.as_ref()) {
+            Some(ability) => format!("Struck down by {}", ability),
+            None => "Slain in combat".to_string(),
+        }
+    }
+}
+
+/// Power is checked against half the defender's block, with a d6 on top;
+/// magic is checked against the defender's faith and only lands if it
+/// exceeds it.
+fn strike(
+    attacker_name: &str, attacker: &CharacterStats,
+    defender_name: &str, defender: &mut CharacterStats,
+    rng: &mut RandomState
+) -> Attack {
+    let physical = (attacker.power() + rng.rng.gen_range(1..=6) - defender.block() / 2).max(1);
+    let magical = (attacker.magic() - defender.faith()).max(0);
+
+    let physical = defender.health_mut().take_damage(physical);
+    let magical = defender.health_mut().take_damage(magical);
+
+    Attack { physical, magical, ..Attack::new(attacker_name, defender_name) }
+}
+
+/// Resolves an ability, falling back to a regular strike
+fn use_ability(
+    attacker_name: &str, attacker: &mut CharacterStats,
+    defender_name: &str, defender: &mut CharacterStats,
+    ability: Option<Ability>, rng: &mut RandomState
+) -> Attack {
+    let ability = match ability {
+        Some(a) => a,
+        None => return strike(attacker_name, attacker, defender_name, defender, rng),
+    };
+
+    let base = Attack::new(attacker_name, defender_name);
+    let mut attack = match ability.effect {
+        AbilityEffect::Strike(bonus) => {
+            let mut attack = strike(attacker_name, attacker, defender_name, defender, rng);
+            attack.physical += defender.health_mut().take_damage(bonus);
+            attack
+        },
+        AbilityEffect::Drain(amount) => {
+            let drained = defender.health_mut().take_damage(amount + attacker.magic() / 2);
+            Attack { magical: drained, healed: attacker.health_mut().heal(drained), ..base }
+        },
+        AbilityEffect::Blast(amount) => Attack {
+            magical: defender.health_mut().take_damage(amount + attacker.magic() / 2),
+            ..base
+        },
+        AbilityEffect::Heal(amount) => Attack { healed: attacker.health_mut().heal(amount), ..base },
+        AbilityEffect::Pray(amount) => {
+            let amount = amount + attacker.faith() / 2;
+            let (healed, shielded) = attacker.health_mut().overheal(amount);
+            Attack { healed, shielded, ..base }
+        },
+        AbilityEffect::Shield(amount) => {
+            let amount = amount + attacker.block() / 4;
+            Attack { shielded: attacker.health_mut().shield(amount), ..base }
+        },
+        AbilityEffect::Afflict { stat, amount, turns } => {
+            defender.apply(stat, Modifier { name: ability.name.clone(), amount, turns });
+            Attack {
+                effect: Some(AppliedEffect { target: defender_name.to_string(), stat, amount, turns }),
+                ..base
+            }
+        },
+        AbilityEffect::Bless { stat, amount, turns } => {
+            attacker.apply(stat, Modifier { name: ability.name.clone(), amount, turns });
+            Attack {
+                effect: Some(AppliedEffect { target: attacker_name.to_string(), stat, amount, turns }),
+                ..base
+            }
+        },
+        AbilityEffect::Stun(turns) => {
+            defender.stun(turns);
+            Attack { stun: turns, ..base }
+        },
+    };
+
+    attack.ability = Some(ability.name);
+    attack
+}
+
+impl std::fmt::Display for Combat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{} {}", "Round".bold(), self.round())?;
+        match self.enemy.boss() {
+            Some(script) => writeln!(f, "{}, the {} {} {}",
+                self.enemy.get_name().red().bold(), self.enemy.get_kind(), self.enemy.get_tier(),
+                format!("(phase {} of {})", script.phase(), script.phase_count()).black()
+            )?,
+            None => writeln!(f, "{} {}", self.enemy.get_name().red().bold(), self.enemy.get_tier())?,
+        }
+        write!(f, "{:3}", self.enemy.stats().health())
+    }
+}
+
+/// Pits the party against the monster and puts the dungeon in combat
+pub fn begin_combat(meta: &mut Meta, enemy: Monster) -> Result<Combat, ClrpgError> {
+    meta.transition(Transition::Engage)?;
+
+    debugln!("Spawned {} ({})", enemy.get_name(), enemy.get_kind());
+
+    let combat = Combat::new(enemy);
+    create_combat(combat.clone())?;
+
+    write_meta(meta)?;
+
+    Ok(combat)
+}
+
+pub fn create_combat(combat: Combat) -> Result<(), ClrpgError> {
+    write_combat(&combat)
+}
+
+pub fn write_combat(combat: &Combat) -> Result<(), ClrpgError> {
+    write_to_dir(&combat, COMBAT_FILE_NAME)
+}
+
+pub fn require_combat() -> Result<Combat, ClrpgError> {
+    require_file(COMBAT_FILE_NAME)
+}
+
+pub fn delete_combat() -> Result<(), ClrpgError> {
+    if check_dir(COMBAT_FILE_NAME)? {
+        delete_file(COMBAT_FILE_NAME)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{bestiary::test_monster, common::tests::test_character};
+
+    fn enemy(health: i32, power: i32, speed: i32) -> Monster {
+        test_monster("Test Rat", CharacterStats::new(health, power, 1, 1, 1, speed))
+    }
+
+    fn curr(stats: &CharacterStats) -> i64 {
+        serde_json::to_value(stats.health()).unwrap()["curr"].as_i64().unwrap()
+    }
+
+    #[test]
+    fn the_faster_combatant_strikes_first() {
+        let mut rng = RandomState::seeded(7);
+        let mut party = [test_character("Ana", CharacterStats::new(100, 5, 5, 1, 1, 12))];
+
+        let mut combat = Combat::new(enemy(100, 5, 3));
+        combat.resolve_round(&mut party, &mut rng);
+        let attackers = combat.last_round().unwrap().attacks.iter()
+            .map(|attack| attack.attacker.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(attackers, ["Ana", "Test Rat"]);
+
+        let mut combat = Combat::new(enemy(100, 5, 19));
+        combat.resolve_round(&mut party, &mut rng);
+        assert_eq!(combat.last_round().unwrap().attacks[0].attacker, "Test Rat");
+    }
+
+    #[test]
+    fn exhausted_members_strike_last_whatever_their_speed() {
+        let mut rng = RandomState::seeded(7);
+        let mut party = [
+            test_character("Ana", CharacterStats::new(100, 5, 5, 1, 1, 19)),
+            test_character("Bo", CharacterStats::new(100, 5, 5, 1, 1, 2)),
+        ];
+        for _ in 0..EXHAUSTION_TURNS {
+            party[0].tire();
+        }
+
+        let mut combat = Combat::new(enemy(100, 5, 10));
+        combat.resolve_round(&mut party, &mut rng);
+        let attackers = combat.last_round().unwrap().attacks.iter()
+            .map(|attack| attack.attacker.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(attackers, ["Test Rat", "Bo", "Ana"]);
+    }
+
+    #[test]
+    fn strikes_take_health_off_the_defender() {
+        let mut rng = RandomState::seeded(7);
+        let mut party = [test_character("Ana", CharacterStats::new(100, 5, 5, 1, 1, 10))];
+        let mut combat = Combat::new(enemy(100, 5, 10));
+
+        assert_eq!(combat.resolve_round(&mut party, &mut rng), CombatOutcome::Ongoing);
+
+        let round = combat.last_round().unwrap();
+        let dealt = |defender: &str| round.attacks.iter()
+            .filter(|attack| attack.defender == defender)
+            .map(|attack| (attack.physical + attack.magical) as i64)
+            .sum::<i64>();
+        assert!(dealt("Ana") > 0);
+        assert_eq!(curr(party[0].stats()), 100 - dealt("Ana"));
+        assert_eq!(curr(combat.enemy().stats()), 100 - dealt("Test Rat"));
+    }
+
+    #[test]
+    fn the_fight_ends_when_someone_falls() {
+        let mut rng = RandomState::seeded(7);
+
+        let mut party = [test_character("Ana", CharacterStats::new(100, 15, 5, 1, 1, 10))];
+        let mut combat = Combat::new(enemy(1, 5, 1));
+        assert_eq!(combat.resolve_round(&mut party, &mut rng), CombatOutcome::Victory);
+        // the enemy never got to strike back
+        assert_eq!(combat.last_round().unwrap().attacks.len(), 1);
+        assert!(!combat.enemy().is_alive());
+
+        let mut party = [test_character("Bo", CharacterStats::new(1, 1, 1, 1, 1, 1))];
+        let mut combat = Combat::new(enemy(100, 15, 19));
+        assert_eq!(combat.resolve_round(&mut party, &mut rng), CombatOutcome::Defeat);
+        assert!(!party[0].is_alive());
+    }
+
+    #[test]
+    fn rounds_survive_being_written_out() {
+        let mut rng = RandomState::seeded(7);
+        let mut party = [test_character("Ana", CharacterStats::new(100, 5, 5, 1, 1, 10))];
+        let mut combat = Combat::new(enemy(100, 5, 10));
+        combat.resolve_round(&mut party, &mut rng);
+        combat.resolve_round(&mut party, &mut rng);
+
+        let json = serde_json::to_string(&combat).unwrap();
+        let read: Combat = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.round(), 2);
+        assert_eq!(read.last_round().unwrap().round, 2);
+        assert_eq!(
+            serde_json::to_string(&read.last_round().unwrap().attacks).unwrap(),
+            serde_json::to_string(&combat.last_round().unwrap().attacks).unwrap()
+        );
+        assert_eq!(curr(read.enemy().stats()), curr(combat.enemy().stats()));
+    }
+}
@@ -1,228 +1,173 @@
-use std::{path::Path, fs::{write, create_dir, remove_dir_all, read_to_string}, cell::RefCell, fmt::Debug};
+use std::{path::Path, fs::{write, create_dir, remove_dir_all, remove_file, read_to_string, OpenOptions}, io::Write, cell::RefCell, fmt::Debug};
 use colored::Colorize;
 use rand::{prelude::*};
 use serde::{Deserialize, Serialize};
 
 use tabled::Tabled;
 
-use crate::{infoln, warnln};
+use crate::{debugln, warnln};
+use super::{error::ClrpgError, bestiary::Ability, classes::CharacterClass, items::{Inventory, Item}};
 
 pub const ROOT_FOLDER_NAME: &str = ".dungeon";
 pub const CHAR_FOLDER_NAME: &str = ".dungeon/.characters";
 pub const RAND_FILE_NAME: &str = ".dungeon/.rand";
 pub const META_FILE_NAME: &str = ".dungeon/.meta";
+pub const COMBAT_FILE_NAME: &str = ".dungeon/.combat";
+pub const FLOORS_FOLDER_NAME: &str = ".dungeon/.floors";
+pub const HISTORY_FILE_NAME: &str = ".dungeon/.history";
+pub const HALL_FOLDER_NAME: &str = ".dungeon/.hall";
+pub const GRAVEYARD_FOLDER_NAME: &str = ".dungeon/.graveyard";
+pub const CLASSES_FILE_NAME: &str = ".dungeon/classes.json";
+pub const TREASURY_FILE_NAME: &str = ".dungeon/.treasury";
+pub const LEDGER_FILE_NAME: &str = ".dungeon/.ledger";
 
 /// Checks if the path exists
-fn check_dir<T>(path: &T) -> Result<bool, String>
+pub(crate) fn check_dir<T>(path: &T) -> Result<bool, ClrpgError>
 where 
     T: AsRef<std::ffi::OsStr> + std::fmt::Display + ?Sized {
-    match Path::new(path).try_exists() {
-        Err(err) => Err(
-            format!(
-                "Unable to determine existence of {}: {}", 
-                path, err.to_string()
-            )
-        ),
-        Ok(t) => Ok(t)
-    }
+    Path::new(path).try_exists()
+        .map_err(|err| ClrpgError::io("determine existence of", path, err))
 }
 
-/// Checks if the path exists
-fn write_to_dir<T, P>(value: &T, path: &P) -> Result<(), String>
+/// Serializes the value and writes it to the path
+pub(crate) fn write_to_dir<T, P>(value: &T, path: &P) -> Result<(), ClrpgError>
 where 
     T: Serialize, 
     P: AsRef<std::path::Path> + std::fmt::Display + ?Sized {
     let json = serde_json::to_string_pretty(&value).unwrap();
-    match write(path, &json.as_bytes()) {
-        Err(err) => Err(
-            format!(
-                "Unable to write to {}: {}", 
-                path, err.to_string()
-            )
-        ),
-        _ => Ok(())
-    }
+    write(path, json.as_bytes())
+        .map_err(|err| ClrpgError::io("write to", path, err))
 }
 
 
-fn delete_dir<T>(path: &T) -> Result<(), String> 
+fn delete_dir<T>(path: &T) -> Result<(), ClrpgError> 
 where 
     T: AsRef<std::path::Path> + std::fmt::Display + ?Sized{
-    match remove_dir_all(path) {
-        Err(err) => Err(
-            format!(
-                "Unable to remove {}: {}", 
-                path, err.to_string()
-            )
-        ),
-        Ok(_) => {
-            Ok(())
-        }
-        
-    }
+    remove_dir_all(path)
+        .map_err(|err| ClrpgError::io("remove", path, err))
 }
 
+/// Appends the value as a single line of json to the path
+pub(crate) fn append_to_file<T, P>(value: &T, path: &P) -> Result<(), ClrpgError>
+where 
+    T: Serialize, 
+    P: AsRef<std::path::Path> + std::fmt::Display + ?Sized {
+    let json = serde_json::to_string(&value).unwrap();
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", json))
+        .map_err(|err| ClrpgError::io("write to", path, err))
+}
 
-fn require_file_strict<T, P>(path: &P) -> Result<T, String>
+/// Reads a file of json lines, skipping entries that cannot be parsed. A
+/// missing file has no entries yet.
+pub(crate) fn read_lines<T, P>(path: &P) -> Vec<T>
 where 
     T: for<'a> Deserialize<'a>, 
     P: AsRef<std::path::Path> + std::fmt::Display + ?Sized {
-    match read_to_string(path) {
-        Ok(json) => {
-            match serde_json::from_str(&json) {
-                Ok(v) => {
-                    Ok(v)
-                },
-                Err(err) => {
-                    crate::errln!(
-                        "{} is corrupted: {}", 
-                        path, err.to_string()
-                    );
-                    return Err(
-                        format!(
-                            "The dungeon is corrupted! {}{} {} {}{}", 
-                            "(use \"".white(),
-                            "clrpg".yellow(), 
-                            "init".black(),
-                            "--force".black(),
-                            "\" to create a new dungeon)".white()
-                        )
-                    );
-
-                },
-            }
-        },
-        Err(err) => {
-            match err.kind() {
-                std::io::ErrorKind::NotFound => {
-                    crate::errln!(
-                        "Required {} is missing!", 
-                        path
-                    );
-
-                    return Err(
-                        format!(
-                            "The dungeon is corrupted! {}{} {} {}{}", 
-                            "(use \"".white(),
-                            "clrpg".yellow(), 
-                            "init".black(),
-                            "--force".black(),
-                            "\" to create a new dungeon)".white()
-                        )
-                    );
-                },
-                _ => {
-
-                    crate::errln!(
-                        "Unable to read from {}: {}", 
-                        path, err.to_string()
-                    );
-
-                    return Err(
-                        format!(
-                            "{}", 
-                            "Unexpected error occured."
-                        )
-                    );
-                }
-            }
-        },
-    }    
+    let lines = match read_to_string(path) {
+        Ok(lines) => lines,
+        Err(_) => return vec![],
+    };
+
+    lines.lines()
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                warnln!("{} has a corrupted entry: {}", path, err);
+                None
+            },
+        })
+        .collect()
+}
 
+/// Replaces the whole file with the values, one line of json each
+pub(crate) fn write_lines<T, P>(values: &[T], path: &P) -> Result<(), ClrpgError>
+where 
+    T: Serialize, 
+    P: AsRef<std::path::Path> + std::fmt::Display + ?Sized {
+    let lines = values.iter()
+        .map(|value| serde_json::to_string(value).unwrap() + "\n")
+        .collect::<String>();
+    write(path, lines.as_bytes())
+        .map_err(|err| ClrpgError::io("write to", path, err))
+}
 
+pub(crate) fn create_folder<T>(path: &T) -> Result<(), ClrpgError> 
+where 
+    T: AsRef<std::path::Path> + std::fmt::Display + ?Sized{
+    create_dir(path)
+        .map_err(|err| ClrpgError::io("create", path, err))
 }
 
+pub(crate) fn delete_file<T>(path: &T) -> Result<(), ClrpgError> 
+where 
+    T: AsRef<std::path::Path> + std::fmt::Display + ?Sized{
+    remove_file(path)
+        .map_err(|err| ClrpgError::io("remove", path, err))
+}
 
-fn require_file<T, P>(path: &P) -> Result<T, String>
+/// Reads and parses the file, reporting a missing or unparsable file as
+/// corruption of the dungeon
+fn read_file<T, P>(path: &P, resettable: bool) -> Result<T, ClrpgError>
 where 
     T: for<'a> Deserialize<'a>, 
     P: AsRef<std::path::Path> + std::fmt::Display + ?Sized {
+    let corrupted = || ClrpgError::CorruptFile { path: path.to_string(), resettable };
+
     match read_to_string(path) {
         Ok(json) => {
-            match serde_json::from_str(&json) {
-                Ok(v) => {
-                    Ok(v)
-                },
-                Err(err) => {
-                    crate::errln!(
-                        "{} is corrupted: {}", 
-                        path, err.to_string()
-                    );
-                    
-                    // check if meta is valid
-                    match require_meta() {
-                        Ok(_) => {
-                            return Err(
-                                format!(
-                                    "The dungeon is corrupted!\n   {}{} {}{}\n   {}{} {} {}{}", 
-                                    "(use \"".white(),
-                                    "clrpg".yellow(), 
-                                    "reset".black(),
-                                    "\" to create a new dungeon with the current seed); or".white(),
-                                    "(use \"".white(),
-                                    "clrpg".yellow(), 
-                                    "init".black(),
-                                    "--force".black(),
-                                    "\" to create a new dungeon)".white()
-                                )
-                            );
-                        },
-                        Err(err) => return Err(err)
-                    }
-
-                },
-            }
+            serde_json::from_str(&json).map_err(|err| {
+                crate::errln!(
+                    "{} is corrupted: {}", 
+                    path, err
+                );
+                corrupted()
+            })
+        },
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            crate::errln!(
+                "Required {} is missing!", 
+                path
+            );
+            Err(corrupted())
         },
         Err(err) => {
-            match err.kind() {
-                std::io::ErrorKind::NotFound => {
-                    crate::errln!(
-                        "Required {} is missing!", 
-                        path
-                    );
-
-                    // check if meta is valid
-                    match require_meta() {
-                        Ok(_) => {
-                            return Err(
-                                format!(
-                                    "The dungeon is corrupted!\n   {}{} {}{}\n   {}{} {} {}{}", 
-                                    "(use \"".white(),
-                                    "clrpg".yellow(), 
-                                    "reset".black(),
-                                    "\" to create a new dungeon with the current seed); or".white(),
-                                    "(use \"".white(),
-                                    "clrpg".yellow(), 
-                                    "init".black(),
-                                    "--force".black(),
-                                    "\" to create a new dungeon)".white()
-                                )
-                            );
-                        },
-                        Err(err) => return Err(err)
-                    }
-                },
-                _ => {
-
-                    crate::errln!(
-                        "Unable to read from {}: {}", 
-                        path, err.to_string()
-                    );
-
-                    return Err(
-                        format!(
-                            "{}", 
-                            "Unexpected error occured."
-                        )
-                    );
-                }
-            }
+            crate::errln!(
+                "Unable to read from {}: {}", 
+                path, err
+            );
+            Err(ClrpgError::io("read from", path, err))
         },
     }
 }
 
+fn require_file_strict<T, P>(path: &P) -> Result<T, ClrpgError>
+where 
+    T: for<'a> Deserialize<'a>, 
+    P: AsRef<std::path::Path> + std::fmt::Display + ?Sized {
+    read_file(path, false)
+}
+
+pub(crate) fn require_file<T, P>(path: &P) -> Result<T, ClrpgError>
+where 
+    T: for<'a> Deserialize<'a>, 
+    P: AsRef<std::path::Path> + std::fmt::Display + ?Sized {
+    match read_file(path, true) {
+        // the dungeon can only be reset if the meta is still valid
+        Err(ClrpgError::CorruptFile { .. }) => Err(
+            require_meta()
+                .err()
+                .unwrap_or(ClrpgError::CorruptFile { path: path.to_string(), resettable: true })
+        ),
+        result => result,
+    }
+}
+
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum MetaStatus {
     HelpWanted,
     OutsideTheDungeon,
@@ -234,15 +179,13 @@ impl std::fmt::Display for MetaStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match *self {
             MetaStatus::HelpWanted => {
-                write!(f, "{}\n{}", 
-                    "* Help Wanted! *".yellow(),
-                    "Looking for capable adventurers to subdue the dungeon."
+                write!(f, "{}\nLooking for capable adventurers to subdue the dungeon.",
+                    "* Help Wanted! *".yellow()
                 )
             },
             MetaStatus::OutsideTheDungeon => {
-                write!(f, "{}\n{}", 
-                    "Outside the dungeon".bold().green(),
-                    "Preparing for the dive..."
+                write!(f, "{}\nPreparing for the dive...",
+                    "Outside the dungeon".bold().green()
                 )
             },
             MetaStatus::InTheDungeon => {
@@ -255,50 +198,417 @@ impl std::fmt::Display for MetaStatus {
     }
 }
 
+/// Where the party stands in the dungeon
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Position {
+    pub floor: u32,
+    pub room: usize,
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Floor {}, room {}", self.floor, self.room)
+    }
+}
+
+/// Every way the dungeon can move between `MetaStatus`es
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Transition {
+    /// An adventurer waits at the mouth of the dungeon
+    Wait,
+    /// The waiting adventurer steps into the dungeon
+    Enter,
+    /// The adventurer moves between rooms
+    Explore,
+    /// The adventurer is drawn into combat
+    Engage,
+    /// The adventurer defeats their foe
+    Victory,
+    /// The adventurer falls in combat
+    Defeat,
+    /// The adventurer runs back to the tavern
+    Flee,
+}
+
+impl Transition {
+    /// The status reached by taking the transition from the given status,
+    /// or `None` if the transition is not allowed
+    fn target(&self, from: MetaStatus) -> Option<MetaStatus> {
+        use MetaStatus::*;
+        match (*self, from) {
+            (Transition::Wait, HelpWanted | OutsideTheDungeon) => Some(OutsideTheDungeon),
+            (Transition::Enter, OutsideTheDungeon) => Some(InTheDungeon),
+            (Transition::Explore, InTheDungeon) => Some(InTheDungeon),
+            (Transition::Engage, InTheDungeon) => Some(InCombat),
+            (Transition::Victory, InCombat) => Some(InTheDungeon),
+            (Transition::Defeat, InCombat) => Some(HelpWanted),
+            (Transition::Flee, OutsideTheDungeon | InTheDungeon | InCombat) => Some(HelpWanted),
+            _ => None,
+        }
+    }
+}
+
+/// A transition that is not allowed from the current status
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionError {
+    pub transition: Transition,
+    pub from: MetaStatus,
+    pub character: Option<String>,
+}
+
+impl TransitionError {
+    /// Suggestion of what to do instead
+    pub fn hint(&self) -> Option<String> {
+        match (self.transition, self.from) {
+            (_, MetaStatus::HelpWanted) => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "character wait <NAME>".black(),
+                    "\" to wait an adventurer)".white()
+                )
+            ),
+            (Transition::Wait | Transition::Enter, _) => None,
+            (_, MetaStatus::OutsideTheDungeon) => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "door".black(),
+                    "\" to enter the dungeon)".white()
+                )
+            ),
+            (_, MetaStatus::InCombat) => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "run".black(),
+                    "\" to flee the dungeon)".white()
+                )
+            ),
+            (_, MetaStatus::InTheDungeon) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for TransitionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match &self.character {
+            Some(name) => name.bold().to_string(),
+            None => "The adventurer".to_string(),
+        };
+
+        match (self.transition, self.from) {
+            (_, MetaStatus::HelpWanted) => write!(f, "No one is in the dungeon right now!"),
+            (Transition::Wait, _) => write!(f, "{} is already attempting the dungeon.", name),
+            (Transition::Enter, _) => write!(f, "{} is already inside the dungeon.", name),
+            (_, MetaStatus::OutsideTheDungeon) => write!(f, "{} has not entered the dungeon yet!", name),
+            (_, MetaStatus::InCombat) => write!(f, "{} is locked in combat!", name),
+            (_, MetaStatus::InTheDungeon) => write!(f, "{} is not in combat.", name),
+        }
+    }
+}
+
+/// Entry in `.dungeon/.history`, one per transition taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransitionRecord {
+    pub at: chrono::NaiveDateTime,
+    pub character: Option<String>,
+    pub transition: Transition,
+    pub from: MetaStatus,
+    pub to: MetaStatus,
+}
+
+/// How a run into the dungeon ended
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum RunOutcome {
+    Ongoing,
+    Fled,
+    Fell,
+}
+
+impl std::fmt::Display for RunOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            RunOutcome::Ongoing => write!(f, "{}", "ONGOING".yellow()),
+            RunOutcome::Fled => write!(f, "{}", "FLED".white()),
+            RunOutcome::Fell => write!(f, "{}", "FELL".red()),
+        }
+    }
+}
+
+/// A single dive of an adventurer into the dungeon, pieced together from
+/// the history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Run {
+    pub entered: chrono::NaiveDateTime,
+    pub left: Option<chrono::NaiveDateTime>,
+    pub victories: u32,
+    pub outcome: RunOutcome,
+}
+
+/// Every run of the adventurer, oldest first
+pub fn runs_of(name: &str, history: &[TransitionRecord]) -> Vec<Run> {
+    let mut runs: Vec<Run> = vec![];
+
+    for record in history.iter().filter(|r| r.character.as_deref() == Some(name)) {
+        if record.transition == Transition::Enter {
+            runs.push(Run { entered: record.at, left: None, victories: 0, outcome: RunOutcome::Ongoing });
+            continue;
+        }
+
+        let run = match runs.last_mut() {
+            Some(run) if run.outcome == RunOutcome::Ongoing => run,
+            _ => continue,
+        };
+        match record.transition {
+            Transition::Victory => run.victories += 1,
+            Transition::Defeat => {
+                run.left = Some(record.at);
+                run.outcome = RunOutcome::Fell;
+            },
+            Transition::Flee => {
+                run.left = Some(record.at);
+                run.outcome = RunOutcome::Fled;
+            },
+            _ => (),
+        }
+    }
+    runs
+}
+
+/// Most adventurers that can attempt the dungeon together
+pub const PARTY_SIZE: usize = 4;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Meta {
     pub seed: u64,
-    pub status: MetaStatus,
-    pub current: Option<String>,
+    status: MetaStatus,
+    /// Adventurers waiting at or inside the dungeon, in the order they joined
+    #[serde(default)]
+    party: Vec<String>,
+    /// The lone adventurer of dungeons from before parties, folded into the
+    /// party when the meta is read
+    #[serde(default, rename = "current", skip_serializing)]
+    legacy_current: Option<String>,
+    #[serde(default)]
+    pub position: Option<Position>,
+    /// Deepest floor opened up by felling the bosses above it
+    #[serde(default = "first_floor")]
+    pub deepest: u32,
+    /// Whether the boss of the final floor has fallen
+    #[serde(default)]
+    pub cleared: bool,
+
+    /// Transitions taken since the meta was loaded, flushed to the
+    /// history when the meta is written
+    #[serde(skip)]
+    pending: Vec<TransitionRecord>,
 }
 
 impl Meta {
     pub fn new(seed: u64) -> Self {
         Self { 
             seed, 
-            status: MetaStatus::OutsideTheDungeon,
-            current: None
+            status: MetaStatus::HelpWanted,
+            party: vec![],
+            legacy_current: None,
+            position: None,
+            deepest: 1,
+            cleared: false,
+            pending: vec![]
+        }
+    }
+
+    pub fn status(&self) -> MetaStatus {
+        self.status
+    }
+
+    pub fn party(&self) -> &[String] {
+        &self.party
+    }
+
+    pub fn in_party(&self, name: &str) -> bool {
+        self.party.iter().any(|member| member == name)
+    }
+
+    /// The member who joined first and speaks for the party
+    pub fn leader(&self) -> Option<&str> {
+        self.party.first().map(String::as_str)
+    }
+
+    /// How the party is referred to in stories: the lone member by name,
+    /// otherwise as a whole
+    pub fn party_name(&self) -> Option<String> {
+        match self.party.as_slice() {
+            [] => None,
+            [name] => Some(name.clone()),
+            _ => Some("The party".to_string()),
+        }
+    }
+
+    /// Checks that the transition is allowed from the current status
+    pub fn check(&self, transition: Transition) -> Result<MetaStatus, TransitionError> {
+        transition.target(self.status).ok_or_else(|| TransitionError {
+            transition,
+            from: self.status,
+            character: self.party_name(),
+        })
+    }
+
+    /// Checks that the current status is one of the given ones, for commands
+    /// that look at the dungeon without changing it
+    pub fn require_status(&self, allowed: &[MetaStatus], transition: Transition) -> Result<(), TransitionError> {
+        if allowed.contains(&self.status) {
+            Ok(())
+        } else {
+            Err(TransitionError {
+                transition,
+                from: self.status,
+                character: self.party_name(),
+            })
+        }
+    }
+
+    /// Takes the transition with the whole party, recording it for every
+    /// member to be written to the history.
+    ///
+    /// Leaving the dungeon forgets the position, and going back to
+    /// `HelpWanted` also breaks up the party.
+    pub fn transition(&mut self, transition: Transition) -> Result<MetaStatus, TransitionError> {
+        let to = self.check(transition)?;
+        let from = self.status;
+
+        if self.party.is_empty() {
+            self.record(None, transition, from, to);
+        }
+        for member in self.party.clone() {
+            self.record(Some(member), transition, from, to);
+        }
+        self.settle(to);
+
+        Ok(from)
+    }
+
+    /// Adds the adventurer to the party waiting outside the dungeon
+    pub fn join(&mut self, name: &str) -> Result<MetaStatus, TransitionError> {
+        let to = self.check(Transition::Wait)?;
+        let from = self.status;
+
+        self.party.push(name.to_string());
+        self.record(Some(name.to_string()), Transition::Wait, from, to);
+        self.settle(to);
+
+        Ok(from)
+    }
+
+    /// Takes a single member out of the party by fleeing or falling,
+    /// recording the transition for them alone. The rest of the party
+    /// carries on, and the dungeon only takes the transition once the last
+    /// member is gone.
+    pub fn part(&mut self, name: &str, transition: Transition) -> Result<MetaStatus, TransitionError> {
+        let to = self.check(transition)?;
+        let from = self.status;
+
+        self.party.retain(|member| member != name);
+        let to = if self.party.is_empty() { to } else { from };
+        self.record(Some(name.to_string()), transition, from, to);
+        self.settle(to);
+
+        Ok(from)
+    }
+
+    fn record(&mut self, character: Option<String>, transition: Transition, from: MetaStatus, to: MetaStatus) {
+        self.pending.push(TransitionRecord {
+            at: chrono::Local::now().naive_local(),
+            character,
+            transition,
+            from,
+            to,
+        });
+    }
+
+    fn settle(&mut self, to: MetaStatus) {
+        self.status = to;
+        match to {
+            MetaStatus::HelpWanted => {
+                self.party.clear();
+                self.position = None;
+            },
+            MetaStatus::OutsideTheDungeon => {
+                self.position = None;
+            },
+            _ => (),
+        }
+    }
+}
+
+fn first_floor() -> u32 {
+    1
+}
+
+/// The status of the dungeon as shown to the player. Once the dungeon is
+/// cleared, nobody is wanted anymore.
+impl std::fmt::Display for Meta {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.status {
+            MetaStatus::HelpWanted if self.cleared => {
+                write!(f, "{}\nThe masters of every floor have fallen. The dungeon lies quiet.",
+                    "* Dungeon Cleared! *".green().bold()
+                )
+            },
+            status => write!(f, "{}", status),
         }
     }
 }
 
 /// Checks if meta file exists
-pub fn _check_meta() -> Result<bool, String> {
+pub fn _check_meta() -> Result<bool, ClrpgError> {
     check_dir(META_FILE_NAME)
 }
 
-pub fn create_meta(meta: Meta) -> Result<(), String> {
-    write_meta(&meta)
+pub fn create_meta(mut meta: Meta) -> Result<(), ClrpgError> {
+    write_meta(&mut meta)
+}
+
+/// Writes the meta, appending any transitions taken to the history
+pub fn write_meta(meta: &mut Meta) -> Result<(), ClrpgError> {
+    write_to_dir(&meta, META_FILE_NAME)?;
+    for record in meta.pending.drain(..) {
+        append_to_file(&record, HISTORY_FILE_NAME)?;
+    }
+    Ok(())
+}
+
+pub fn require_meta() -> Result<Meta, ClrpgError> {
+    let mut meta: Meta = require_file_strict(META_FILE_NAME)?;
+    if let Some(current) = meta.legacy_current.take() {
+        if !meta.in_party(&current) {
+            meta.party.insert(0, current);
+        }
+    }
+    Ok(meta)
 }
 
-pub fn write_meta(meta: &Meta) -> Result<(), String> {
-    write_to_dir(&meta, META_FILE_NAME)
+/// Reads every recorded transition, skipping entries that cannot be parsed
+pub fn load_history() -> Vec<TransitionRecord> {
+    read_lines(HISTORY_FILE_NAME)
 }
 
-pub fn require_meta() -> Result<Meta, String> {
-    require_file_strict(META_FILE_NAME)
+/// Replaces the whole history, used when past entries have to be amended
+fn write_history(history: &[TransitionRecord]) -> Result<(), ClrpgError> {
+    write_lines(history, HISTORY_FILE_NAME)
 }
 
 
 
-thread_local!(static ACTIVE: RefCell<bool> = RefCell::new(false));
+thread_local!(static ACTIVE: RefCell<bool> = const { RefCell::new(false) });
 
 pub type Prng = rand_pcg::Pcg64Mcg;
 pub struct RandomState {
     pub rng: Prng,
 }
 
-pub fn create_rand(seed: Option<u64>) -> Result<u64, String> {
+pub fn create_rand(seed: Option<u64>) -> Result<u64, ClrpgError> {
     if let Some(s) = seed {
         write_rand(&Prng::seed_from_u64(s))?;
         Ok(s)
@@ -309,13 +619,13 @@ pub fn create_rand(seed: Option<u64>) -> Result<u64, String> {
     }
 }
 
-fn write_rand(rng: &Prng) -> Result<(), String> {
+fn write_rand(rng: &Prng) -> Result<(), ClrpgError> {
     write_to_dir(&rng, RAND_FILE_NAME)
 }
 
 
 impl RandomState {
-    pub fn single_use() -> Result<Self, String> {
+    pub fn single_use() -> Result<Self, ClrpgError> {
 
         ACTIVE.with(|b| {
             let mut inner = b.borrow_mut();
@@ -332,6 +642,20 @@ impl RandomState {
 
     }
 
+    /// Stream from a fixed seed that is never written back, for tests
+    #[cfg(test)]
+    pub fn seeded(seed: u64) -> Self {
+        RandomState { rng: Prng::seed_from_u64(seed) }
+    }
+
+    /// Runs `f` on a stream seeded from the given value, leaving the shared
+    /// stream untouched
+    pub fn with_seed<T>(&mut self, seed: u64, f: impl FnOnce(&mut RandomState) -> T) -> T {
+        let shared = std::mem::replace(&mut self.rng, Prng::seed_from_u64(seed));
+        let result = f(self);
+        self.rng = shared;
+        result
+    }
 
     pub fn generate_id(&mut self) -> String {
         // just randomly select 8 bytes of our alphabet and return
@@ -348,7 +672,7 @@ impl RandomState {
         ret
     }
 
-    pub fn generate_name(&mut self) -> Result<String, String> {
+    pub fn generate_name(&mut self) -> Result<String, ClrpgError> {
 
         let mut i:u32 = 0;
         const MAX_RETRIES: u32 = 100;
@@ -362,7 +686,7 @@ impl RandomState {
             let name = format!("{}{}", adjective, animal);
             match check_character(&name) {
                 Ok(t) if !t => {
-                    infoln!("Retried name generation {} times", i);
+                    debugln!("Retried name generation {} times", i);
                     return Ok(name);
                 },
                 _ => i += 1
@@ -370,7 +694,7 @@ impl RandomState {
         }
 
         crate::errln!("Max retries for name generation exceeded!");
-        Err("Unexpected error occured.".to_string())
+        Err(ClrpgError::Unexpected("Max retries for name generation exceeded".to_string()))
     }
 
 
@@ -379,10 +703,11 @@ impl RandomState {
 
 impl Drop for RandomState {
     fn drop(&mut self) {
-        // serialize rng      
-        
-        if let Err(err) = write_rand(&self.rng) {
-            panic!("{}", err);
+        // serialize rng, unless it is a test stream with no dungeon behind it
+        if !cfg!(test) {
+            if let Err(err) = write_rand(&self.rng) {
+                panic!("{}", err);
+            }
         }
 
         ACTIVE.with(|b| {
@@ -394,27 +719,16 @@ impl Drop for RandomState {
 
 
 
-pub fn create_root() -> Result<(), String> {
-
-    match create_dir(ROOT_FOLDER_NAME) {
-        Err(err) => Err(
-            format!(
-                "Unable to create {}: {}", 
-                ROOT_FOLDER_NAME, err.to_string()
-            )
-        ),
-        Ok(_) => {
-            Ok(())
-        }
-    }
+pub fn create_root() -> Result<(), ClrpgError> {
+    create_folder(ROOT_FOLDER_NAME)
 }
 
 /// Checks if root folder exists
-pub fn check_root() -> Result<bool, String> {
+pub fn check_root() -> Result<bool, ClrpgError> {
     check_dir(ROOT_FOLDER_NAME)
 }
 
-pub fn require_root() -> Result<(), String> {
+pub fn require_root() -> Result<(), ClrpgError> {
     if !check_root()? {
         crate::errln!(
             "Missing {}! Please run command: [ {} {} ]", 
@@ -423,41 +737,22 @@ pub fn require_root() -> Result<(), String> {
             "init".black()
         );
 
-        return Err(
-            format!(
-                "There is no dungeon! {}{} {}{}", 
-                "(use \"".white(),
-                "clrpg".yellow(), 
-                "init".black(),
-                "\" to create the dungeon)".white()
-            )
-        );
+        return Err(ClrpgError::MissingRoot);
     }
     Ok(())
 }
 
 
-pub fn create_char() -> Result<(), String> {
-
-    match create_dir(CHAR_FOLDER_NAME) {
-        Err(err) => Err(
-            format!(
-                "Unable to create {}: {}", 
-                CHAR_FOLDER_NAME, err.to_string()
-            )
-        ),
-        Ok(_) => {
-            Ok(())
-        }
-    }
+pub fn create_char() -> Result<(), ClrpgError> {
+    create_folder(CHAR_FOLDER_NAME)
 }
 
 /// Checks if character folder exists
-pub fn check_char() -> Result<bool, String> {
+pub fn check_char() -> Result<bool, ClrpgError> {
     check_dir(CHAR_FOLDER_NAME)
 }
 
-pub fn require_char() -> Result<(), String> {
+pub fn require_char() -> Result<(), ClrpgError> {
     if !check_char()? {
 
         crate::errln!(
@@ -466,38 +761,36 @@ pub fn require_char() -> Result<(), String> {
         );
 
         // check if meta is valid
-        match require_meta() {
-            Ok(_) => {
-                return Err(
-                    format!(
-                        "The dungeon is corrupted!\n   {}{} {}{}\n   {}{} {} {}{}", 
-                        "(use \"".white(),
-                        "clrpg".yellow(), 
-                        "reset".black(),
-                        "\" to create a new dungeon with the current seed); or".white(),
-                        "(use \"".white(),
-                        "clrpg".yellow(), 
-                        "init".black(),
-                        "--force".black(),
-                        "\" to create a new dungeon)".white()
-                    )
-                );
-            },
-            Err(err) => return Err(err)
-        }
+        require_meta()?;
+        return Err(ClrpgError::CorruptFile { path: CHAR_FOLDER_NAME.to_string(), resettable: true });
     }
     Ok(())
 }
 
 /// Checks if character file exists
-pub fn check_character(name: &str) -> Result<bool, String> {
+pub fn check_character(name: &str) -> Result<bool, ClrpgError> {
     check_dir(&format!("{}/{}", CHAR_FOLDER_NAME, name))
 }
 
 
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+/// Condition of a character, from the most to the least severe. Only the
+/// most severe one that applies is kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
 pub enum CharacterStatus {
     Healthy,
+    /// Health is low; cannot enter the dungeon until rested
+    Wounded,
+    /// Losing health every turn until the poison wears off or is rested away
+    Poisoned,
+    /// Loses the next turn in combat
+    Stunned,
+    /// Has gone too long without rest; always strikes last and cannot
+    /// enter the dungeon until rested
+    Exhausted,
+    /// Recovering in the tavern until waited again
+    Resting,
+    Dead,
+    Retired,
 }
 
 impl std::fmt::Display for CharacterStatus {
@@ -508,23 +801,103 @@ impl std::fmt::Display for CharacterStatus {
             len = w;
         } 
 
-        match *self {
-            CharacterStatus::Healthy => write!(f, "{}{:indent$}", "HEALTHY".green(), "",indent=len.saturating_sub(7)),
-        }
+        let label = match *self {
+            CharacterStatus::Healthy => "HEALTHY".green(),
+            CharacterStatus::Wounded => "WOUNDED".yellow(),
+            CharacterStatus::Poisoned => "POISONED".magenta(),
+            CharacterStatus::Stunned => "STUNNED".cyan(),
+            CharacterStatus::Exhausted => "EXHAUSTED".yellow(),
+            CharacterStatus::Resting => "RESTING".blue(),
+            CharacterStatus::Dead => "DEAD".red(),
+            CharacterStatus::Retired => "RETIRED".white(),
+        };
+        let width = label.len();
+        write!(f, "{}{:indent$}", label, "", indent=len.saturating_sub(width))
     }
 }
 
+/// Health, in percent of the maximum, below which a character is wounded
+pub const WOUNDED_PERCENT: i32 = 30;
+/// Turns taken without rest after which a character is exhausted
+pub const EXHAUSTION_TURNS: u32 = 40;
+
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct HealthStat {
     max: i32,
     curr: i32,
     temp: i32,
+
+    /// Poison (negative) and regeneration (positive) applied every turn
+    #[serde(default)]
+    over_time: Vec<Modifier>,
 }
 
 impl HealthStat {
     fn new(max: i32) -> Self {
-        HealthStat { max, curr: max, temp: 0 }
+        HealthStat { max, curr: max, temp: 0, over_time: vec![] }
+    }
+
+    pub fn is_depleted(&self) -> bool {
+        self.curr <= 0
+    }
+
+    /// Applies the damage, temporary health first. Returns the damage dealt,
+    /// counting both the temporary health absorbed and the health lost, but
+    /// nothing past what was left of either.
+    pub fn take_damage(&mut self, amount: i32) -> i32 {
+        let absorbed = amount.clamp(0, self.temp.max(0));
+        self.temp -= absorbed;
+
+        let lost = (amount - absorbed).clamp(0, self.curr.max(0));
+        self.curr -= lost;
+        absorbed + lost
+    }
+
+    /// Raises the maximum, healing by the same amount
+    fn grow(&mut self, amount: i32) {
+        self.max += amount;
+        self.curr += amount;
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.over_time.iter().any(|m| m.amount < 0)
+    }
+
+    /// Current health in percent of the maximum
+    pub fn percent(&self) -> i32 {
+        if self.max <= 0 {
+            return 0;
+        }
+        self.curr * 100 / self.max
+    }
+
+    /// Restores health up to the maximum and returns the amount actually healed
+    pub fn heal(&mut self, amount: i32) -> i32 {
+        let healed = amount.clamp(0, (self.max - self.curr).max(0));
+        self.curr += healed;
+        healed
+    }
+
+    /// Most temporary health the character can hold at once
+    fn temp_cap(&self) -> i32 {
+        self.max * TEMP_HEALTH_PERCENT / 100
+    }
+
+    /// Adds temporary health that absorbs damage before the current health,
+    /// up to the cap. Returns the amount actually gained.
+    pub fn shield(&mut self, amount: i32) -> i32 {
+        let gained = amount.clamp(0, (self.temp_cap() - self.temp).max(0));
+        self.temp += gained;
+        gained
+    }
+
+    /// Heals like `heal`, but whatever would go past the maximum is kept as
+    /// temporary health instead. Returns the health healed and the temporary
+    /// health gained.
+    pub fn overheal(&mut self, amount: i32) -> (i32, i32) {
+        let healed = self.heal(amount);
+        (healed, self.shield(amount - healed))
     }
 }
 
@@ -582,22 +955,128 @@ impl std::fmt::Display for HealthStat {
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-pub struct RegStat {
-    max: i32,
-    temp: Vec<(i32, i32)>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Stat {
+    Health,
+    Power,
+    Block,
+    Magic,
+    Faith,
+    Speed,
 }
 
-impl RegStat {
-    fn new(max: i32) -> Self {
-        RegStat { max, temp: vec![] }
+impl std::fmt::Display for Stat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Stat::Health => write!(f, "HEALTH"),
+            Stat::Power => write!(f, "POWER"),
+            Stat::Block => write!(f, "BLOCK"),
+            Stat::Magic => write!(f, "MAGIC"),
+            Stat::Faith => write!(f, "FAITH"),
+            Stat::Speed => write!(f, "SPEED"),
+        }
     }
 }
 
-impl std::fmt::Display for RegStat {
+/// A named effect on a stat lasting for a number of turns. Turns are
+/// combat rounds and moves through the dungeon.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Modifier {
+    pub name: String,
+    pub amount: i32,
+    pub turns: i32,
+}
+
+impl Modifier {
+    /// Turns of a modifier that lasts until it is taken off, like the
+    /// bonus of a piece of equipment
+    pub const LASTING: i32 = -1;
+
+    pub fn is_lasting(&self) -> bool {
+        self.turns < 0
+    }
+}
+
+/// Pushes the modifier, refreshing an existing one of the same name
+/// instead of stacking them
+fn push_modifier(modifiers: &mut Vec<Modifier>, modifier: Modifier) {
+    modifiers.retain(|m| m.name != modifier.name);
+    modifiers.push(modifier);
+}
+
+/// Counts a turn down on every modifier, returning the names of the
+/// ones that wore off
+fn count_down(modifiers: &mut Vec<Modifier>) -> Vec<String> {
+    let mut expired = vec![];
+    modifiers.retain_mut(|m| {
+        if m.is_lasting() {
+            return true;
+        }
+        m.turns -= 1;
+        if m.turns <= 0 {
+            expired.push(m.name.clone());
+        }
+        m.turns > 0
+    });
+    expired
+}
+
+/// What a status effect did to a combatant over a single turn
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Tick {
+    pub target: String,
+    pub name: String,
+    /// Health lost (negative) or recovered (positive)
+    pub amount: i32,
+    pub expired: bool,
+}
+
+impl std::fmt::Display for Tick {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        
-        let mut padding = String::new();
+        if self.amount < 0 {
+            write!(f, "{} suffers {} damage from {}.", self.target, -self.amount, self.name)?;
+        } else if self.amount > 0 {
+            write!(f, "{} recovers {} health from {}.", self.target, self.amount, self.name)?;
+        }
+        if self.expired {
+            if self.amount != 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{} wears off {}.", self.name, self.target)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RegStat {
+    max: i32,
+    temp: Vec<Modifier>,
+}
+
+impl RegStat {
+    fn new(max: i32) -> Self {
+        RegStat { max, temp: vec![] }
+    }
+
+    /// Current value of the stat, including temporary modifiers
+    pub fn value(&self) -> i32 {
+        self.max + self.temp.iter().map(|m| m.amount).sum::<i32>()
+    }
+
+    fn grow(&mut self, amount: i32) {
+        self.max += amount;
+    }
+
+    pub fn modifiers(&self) -> &[Modifier] {
+        &self.temp
+    }
+}
+
+impl std::fmt::Display for RegStat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        
+        let mut padding = String::new();
         if let Some(width) = f.width() {
             padding = String::with_capacity(width);
             for _ in 0..width {
@@ -605,21 +1084,20 @@ impl std::fmt::Display for RegStat {
             }
         }         
         
-        if self.temp.len() == 0 {
+        if self.temp.is_empty() {
             write!(f, "{padding}{:<3}", self.max)
         } else {
-            let mut curr = self.max as i32;
-            let mut mods = curr.to_string();
-            for (modify, _) in &self.temp {
-                mods = format!("{}{:+}", mods, modify);
-                curr += modify;
+            let curr = self.value();
+            let mut mods = self.max.to_string();
+            for modifier in &self.temp {
+                mods = format!("{} {:+} {}", mods, modifier.amount, modifier.name);
             }
-            if curr > self.max as i32 {
+            if curr > self.max {
                 write!(f, "{padding}{:<3} {}", 
                     curr.to_string().green(), 
                     format!("({})", mods).black()
                 )
-            } else if curr < self.max as i32 {
+            } else if curr < self.max {
                 write!(f, "{padding}{:<3} {}", 
                     curr.to_string().red(), 
                     format!("({})", mods).black()
@@ -634,6 +1112,22 @@ impl std::fmt::Display for RegStat {
     }
 }
 
+/// Cap on temporary health, in percent of the maximum health. Only
+/// faith-based healing overheals into it; every other heal stops at the
+/// maximum.
+pub const TEMP_HEALTH_PERCENT: i32 = 25;
+
+/// Starting health of characters without a class
+pub const BASE_HEALTH: i32 = 100;
+
+/// Share of maximum health, in percent, lost for good with every revival
+pub const REVIVAL_HEALTH_PENALTY: i32 = 10;
+/// Points to spread across the regular stats with point-buy
+pub const POINT_BUY_BUDGET: i32 = 50;
+/// Bounds of a regular stat at creation, matching the random rolls
+pub const STAT_MIN: i32 = 1;
+pub const STAT_MAX: i32 = 19;
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CharacterStats {
     health: HealthStat, 
@@ -641,49 +1135,275 @@ pub struct CharacterStats {
     block: RegStat,
     magic: RegStat,
     faith: RegStat,
-    speed: RegStat
+    speed: RegStat,
+
+    /// Combat turns left to skip
+    #[serde(default)]
+    stunned: i32,
 }
 
 
 impl CharacterStats {
+    pub fn new(health: i32, power: i32, block: i32, magic: i32, faith: i32, speed: i32) -> CharacterStats {
+        CharacterStats {
+            health: HealthStat::new(health),
+            power: RegStat::new(power),
+            block: RegStat::new(block),
+            magic: RegStat::new(magic),
+            faith: RegStat::new(faith),
+            speed: RegStat::new(speed),
+            stunned: 0,
+        }
+    }
+
+    pub fn health(&self) -> &HealthStat {
+        &self.health
+    }
+
+    pub fn health_mut(&mut self) -> &mut HealthStat {
+        &mut self.health
+    }
+
+    pub fn power(&self) -> i32 {
+        self.power.value()
+    }
+
+    pub fn block(&self) -> i32 {
+        self.block.value()
+    }
+
+    pub fn magic(&self) -> i32 {
+        self.magic.value()
+    }
+
+    pub fn faith(&self) -> i32 {
+        self.faith.value()
+    }
+
+    pub fn speed(&self) -> i32 {
+        self.speed.value()
+    }
+
+    /// Sum of the regular stats, ignoring modifiers
+    pub fn total(&self) -> i32 {
+        self.power.max + self.block.max + self.magic.max + self.faith.max + self.speed.max
+    }
+
+    /// Every temporary modifier along with the stat it applies to
+    pub fn modifiers(&self) -> Vec<(Stat, &Modifier)> {
+        let health = self.health.over_time.iter().map(|m| (Stat::Health, m));
+        let regular = [
+            (Stat::Power, &self.power),
+            (Stat::Block, &self.block),
+            (Stat::Magic, &self.magic),
+            (Stat::Faith, &self.faith),
+            (Stat::Speed, &self.speed),
+        ]
+        .into_iter()
+        .flat_map(|(stat, reg)| reg.modifiers().iter().map(move |m| (stat, m)));
+
+        health.chain(regular).collect()
+    }
+
+    pub fn stun(&mut self, turns: i32) {
+        self.stunned = self.stunned.max(turns);
+    }
+
+    pub fn is_stunned(&self) -> bool {
+        self.stunned > 0
+    }
+
+    /// Uses up a stunned turn, returning whether the turn has to be skipped
+    pub fn skip_stunned_turn(&mut self) -> bool {
+        if self.stunned > 0 {
+            self.stunned -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Shakes off any stun once the fight is over
+    pub fn clear_stun(&mut self) {
+        self.stunned = 0;
+    }
+
+    fn modifiers_mut(&mut self, stat: Stat) -> &mut Vec<Modifier> {
+        match stat {
+            Stat::Health => &mut self.health.over_time,
+            Stat::Power => &mut self.power.temp,
+            Stat::Block => &mut self.block.temp,
+            Stat::Magic => &mut self.magic.temp,
+            Stat::Faith => &mut self.faith.temp,
+            Stat::Speed => &mut self.speed.temp,
+        }
+    }
+
+    /// Puts a status effect on the stat. Health modifiers are applied every
+    /// turn, the others change the stat until they wear off.
+    pub fn apply(&mut self, stat: Stat, modifier: Modifier) {
+        push_modifier(self.modifiers_mut(stat), modifier);
+    }
+
+    /// Takes the modifier of the given name off the stat
+    pub fn remove(&mut self, stat: Stat, name: &str) {
+        self.modifiers_mut(stat).retain(|m| m.name != name);
+    }
+
+    /// Purges every poison
+    pub fn cure(&mut self) {
+        self.health.over_time.retain(|m| m.amount > 0);
+    }
+
+    /// Applies poison and regeneration, then counts a turn down on every
+    /// status effect. Outside of combat poison is not lethal and leaves the
+    /// target with at least 1 health.
+    pub fn tick(&mut self, target: &str, lethal: bool) -> Vec<Tick> {
+        let mut ticks = vec![];
+
+        for modifier in self.health.over_time.clone() {
+            let amount = if modifier.amount < 0 {
+                let mut damage = -modifier.amount;
+                if !lethal {
+                    damage = damage.min(self.health.curr - 1);
+                }
+                -self.health.take_damage(damage)
+            } else {
+                self.health.heal(modifier.amount)
+            };
+            ticks.push(Tick { target: target.to_string(), name: modifier.name, amount, expired: false });
+        }
+
+        let mut expired = count_down(&mut self.health.over_time);
+        for reg in [&mut self.power, &mut self.block, &mut self.magic, &mut self.faith, &mut self.speed] {
+            expired.extend(count_down(&mut reg.temp));
+        }
+
+        for name in expired {
+            match ticks.iter_mut().find(|t| t.name == name) {
+                Some(tick) => tick.expired = true,
+                None => ticks.push(Tick { target: target.to_string(), name, amount: 0, expired: true }),
+            }
+        }
+
+        ticks.retain(|t| t.amount != 0 || t.expired);
+        ticks
+    }
+
+    /// Rolls the growth of a single level up
+    fn grow(&mut self, rng: &mut RandomState) {
+        self.health.grow(rng.rng.gen_range(5..=10));
+        self.power.grow(rng.rng.gen_range(0..=2));
+        self.block.grow(rng.rng.gen_range(0..=2));
+        self.magic.grow(rng.rng.gen_range(0..=2));
+        self.faith.grow(rng.rng.gen_range(0..=2));
+        self.speed.grow(rng.rng.gen_range(0..=2));
+    }
+
     pub fn from_rng(rng: &mut RandomState) -> CharacterStats {
-        let c = CharacterStats {
-            health: HealthStat::new(100),
+        CharacterStats {
+            health: HealthStat::new(BASE_HEALTH),
             power: RegStat::new(rng.rng.gen_range(1..20)),
             block: RegStat::new(rng.rng.gen_range(1..20)),
             magic: RegStat::new(rng.rng.gen_range(1..20)),
             faith: RegStat::new(rng.rng.gen_range(1..20)),
-            speed: RegStat::new(rng.rng.gen_range(1..20))
-        };
-
-        // c.power.temp.push((2, 1));
-
-        // c.magic.temp.push((-2, 1));
+            speed: RegStat::new(rng.rng.gen_range(1..20)),
+            stunned: 0,
+        }
+    }
+}
 
-        // c.faith.temp.push((1, 1));
-        // c.faith.temp.push((-1, 1));
 
-        
-        // c.speed.temp.push((1, 1));
-        // c.speed.temp.push((-1, 1));
-        // c.speed.temp.push((2, 1));
-        // c.speed.temp.push((-3, 1));
-        // c.speed.temp.push((-4, 1));
+/// How the starting stats were chosen. Along with the dungeon seed this is
+/// enough to recreate the character.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+pub enum Creation {
+    #[default]
+    Random,
+    PointBuy { power: i32, block: i32, magic: i32, faith: i32, speed: i32 },
+    /// The `picked` set, counting from 1, out of `rolls` sets rolled from
+    /// the stream of `Creation::reroll_seed`
+    Reroll { rolls: u32, picked: u32 },
+}
 
-        c
+impl Creation {
+    /// Seed of the stat sets rerolled for a character, mixed from the dungeon
+    /// seed and the character id so the sets do not depend on anything
+    /// played before
+    pub fn reroll_seed(seed: u64, id: &str) -> u64 {
+        id.bytes().fold(seed, |acc, b| (acc ^ b as u64).wrapping_mul(0x0000_0100_0000_01B3))
     }
 }
 
+impl std::fmt::Display for Creation {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Creation::Random => write!(f, "random roll"),
+            Creation::PointBuy { .. } => write!(f, "point-buy"),
+            Creation::Reroll { rolls, picked } => write!(f, "roll {} of {}", picked, rolls),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Deserialize, Serialize, Tabled)]
 pub struct CharacterObj {
     id: String,
     name: String,
+    #[serde(default = "first_level")]
+    level: u32,
+    #[serde(default)]
+    #[tabled(display_with = "display_class")]
+    class: Option<String>,
     status: CharacterStatus,
+    /// Deepest floor set foot on, 0 if never in the dungeon
+    #[serde(default)]
+    #[tabled(rename = "depth", display_with = "display_depth")]
+    deepest: u32,
     created: chrono::NaiveDateTime,
 
+    #[serde(default)]
+    #[tabled(skip)]
+    xp: u32,
+
     #[tabled(skip)]
     stats: CharacterStats,
+
+    #[serde(default)]
+    #[tabled(skip)]
+    abilities: Vec<Ability>,
+
+    #[serde(default)]
+    #[tabled(skip)]
+    creation: Creation,
+
+    /// Turns taken since the last rest
+    #[serde(default)]
+    #[tabled(skip)]
+    fatigue: u32,
+
+    #[serde(default)]
+    #[tabled(skip)]
+    inventory: Inventory,
+}
+
+fn first_level() -> u32 {
+    1
+}
+
+fn display_class(class: &Option<String>) -> String {
+    class.clone().unwrap_or_else(|| "-".to_string())
+}
+
+fn display_depth(depth: &u32) -> String {
+    match depth {
+        0 => "-".to_string(),
+        depth => depth.to_string(),
+    }
+}
+
+/// Total experience needed to reach the level
+pub fn xp_for_level(level: u32) -> u32 {
+    50 * level * level.saturating_sub(1)
 }
 
 
@@ -700,68 +1420,465 @@ impl std::fmt::Display for CharacterObj {
             }
         }
 
-        write!(f, "{padding}{}   {} ({}) \n", self.get_life_string(), self.name.bold(), self.id)?;
+        write!(f, "{padding}{}   {} ({}) ", self.get_life_string(), self.name.bold(), self.id)?;
+        match &self.class {
+            Some(class) => writeln!(f, "{}", class.to_uppercase().cyan())?,
+            None => writeln!(f)?,
+        }
                 // write!(f, 
         //     "{padding}Health: {} ({})\n",
         //     health_to_hearts(self.health), self.health
         // )?;
         // write!(f, "{:>indent$}", self.stats, indent=padding.len())
         
-        write!(f, "{:indent$}\n", self.stats.health, indent=len)?;
-        write!(f, "{padding}POWER:  {}\n", self.stats.power)?;
-        write!(f, "{padding}BLOCK:  {}\n", self.stats.block)?;
-        write!(f, "{padding}MAGIC:  {}\n", self.stats.magic)?;
-        write!(f, "{padding}FAITH:  {}\n", self.stats.faith)?;
-        write!(f, "{padding}SPEED:  {}\n", self.stats.speed)
+        writeln!(f, "{:indent$}", self.stats.health, indent=len)?;
+        writeln!(f, "{padding}LEVEL:  {:<3} {}",
+            self.level,
+            format!("({}/{} XP)", self.xp, xp_for_level(self.level + 1)).black()
+        )?;
+        match self.deepest {
+            0 => writeln!(f, "{padding}DEPTH:  {}", "never entered".black())?,
+            depth => writeln!(f, "{padding}DEPTH:  floor {}", depth)?,
+        }
+        writeln!(f, "{padding}POWER:  {}", self.stats.power)?;
+        writeln!(f, "{padding}BLOCK:  {}", self.stats.block)?;
+        writeln!(f, "{padding}MAGIC:  {}", self.stats.magic)?;
+        writeln!(f, "{padding}FAITH:  {}", self.stats.faith)?;
+        writeln!(f, "{padding}SPEED:  {}", self.stats.speed)
     }
 }
 
 impl CharacterObj {
     pub fn get_life_string(&self) -> String {
-        let life = if self.is_alive() {
-            format!("{}", "ALIVE".green().bold())
-        } else {
-            format!(" {}", "DEAD".red().bold())
-        };
-
-        life
+        format!("{:9}", self.status).bold().to_string()
     }
 
     pub fn get_name(&self) -> &str {
         &self.name
     }
 
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    pub fn get_created(&self) -> chrono::NaiveDateTime {
+        self.created
+    }
+
+    pub fn get_creation(&self) -> &Creation {
+        &self.creation
+    }
+
+    pub fn stats(&self) -> &CharacterStats {
+        &self.stats
+    }
+
+    pub fn stats_mut(&mut self) -> &mut CharacterStats {
+        &mut self.stats
+    }
+
     pub fn is_alive(&self) -> bool {
         self.stats.health.curr > 0
     }
+
+    /// Picks the first class ability whose chance succeeds, if any
+    pub fn roll_ability(&self, rng: &mut RandomState) -> Option<&Ability> {
+        self.abilities.iter()
+            .find(|a| rng.rng.gen_bool(a.chance.clamp(0.0, 1.0)))
+    }
+
+    pub fn get_status(&self) -> CharacterStatus {
+        self.status
+    }
+
+    /// Works out the most severe condition the character is in. Resting and
+    /// retired characters keep their status until they are dead.
+    pub fn refresh_status(&mut self) {
+        self.status = if !self.is_alive() {
+            CharacterStatus::Dead
+        } else if matches!(self.status, CharacterStatus::Resting | CharacterStatus::Retired) {
+            self.status
+        } else if self.stats.is_stunned() {
+            CharacterStatus::Stunned
+        } else if self.stats.health.is_poisoned() {
+            CharacterStatus::Poisoned
+        } else if self.stats.health.percent() < WOUNDED_PERCENT {
+            CharacterStatus::Wounded
+        } else if self.is_exhausted() {
+            CharacterStatus::Exhausted
+        } else {
+            CharacterStatus::Healthy
+        };
+    }
+
+    /// The condition keeping the character from entering the dungeon, if any
+    pub fn unfit(&self) -> Option<CharacterStatus> {
+        if self.stats.health.percent() < WOUNDED_PERCENT {
+            Some(CharacterStatus::Wounded)
+        } else if self.is_exhausted() {
+            Some(CharacterStatus::Exhausted)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.fatigue >= EXHAUSTION_TURNS
+    }
+
+    /// Counts a turn taken in the dungeon towards exhaustion
+    pub fn tire(&mut self) {
+        self.fatigue += 1;
+    }
+
+    pub fn rest(&mut self) {
+        self.status = CharacterStatus::Resting;
+    }
+
+    /// Ends the rest: cures poison and stun, shakes off exhaustion and
+    /// recovers half of the maximum health. Returns the health recovered.
+    pub fn wake(&mut self) -> i32 {
+        self.stats.cure();
+        self.stats.clear_stun();
+        self.fatigue = 0;
+        let healed = self.stats.health.heal(self.stats.health.max / 2);
+
+        self.status = CharacterStatus::Healthy;
+        self.refresh_status();
+        healed
+    }
+
+    pub fn inventory(&self) -> &Inventory {
+        &self.inventory
+    }
+
+    pub fn inventory_mut(&mut self) -> &mut Inventory {
+        &mut self.inventory
+    }
+
+    /// Puts the item on, applying its bonuses. Returns the item it
+    /// replaces, with its bonuses taken off.
+    pub fn equip(&mut self, item: Item) -> Option<Item> {
+        let replaced = self.inventory.equip(item.clone());
+        if let Some(replaced) = &replaced {
+            for bonus in &replaced.bonuses {
+                self.stats.remove(bonus.stat, &replaced.name);
+            }
+        }
+
+        for bonus in &item.bonuses {
+            self.stats.apply(bonus.stat, Modifier {
+                name: item.name.clone(), amount: bonus.amount, turns: Modifier::LASTING
+            });
+        }
+        replaced
+    }
+
+    /// Takes the equipped item off by name, along with its bonuses
+    pub fn unequip(&mut self, name: &str) -> Option<Item> {
+        let item = self.inventory.unequip(name)?;
+        for bonus in &item.bonuses {
+            self.stats.remove(bonus.stat, &item.name);
+        }
+        Some(item)
+    }
+
+    /// Spends the night in a tavern room: wakes up like from a rest, but
+    /// with all health recovered. Returns the health recovered.
+    pub fn lodge(&mut self) -> i32 {
+        let healed = self.wake() + self.stats.health.heal(self.stats.health.max);
+        self.refresh_status();
+        healed
+    }
+
+    pub fn get_deepest(&self) -> u32 {
+        self.deepest
+    }
+
+    /// Records setting foot on the floor at the given depth
+    pub fn reach(&mut self, depth: u32) {
+        self.deepest = self.deepest.max(depth);
+    }
+
+    /// Brings the dead character back, weakened: a share of their maximum
+    /// health is lost for good, they wake with a quarter of what is left
+    /// and the experience gathered towards the next level is forfeit.
+    /// Returns the maximum health and the experience lost.
+    pub fn revive(&mut self) -> (i32, u32) {
+        let health = &mut self.stats.health;
+        let health_lost = (health.max * REVIVAL_HEALTH_PENALTY / 100).max(1);
+        health.max = (health.max - health_lost).max(1);
+        health.curr = (health.max / 4).max(1);
+        health.temp = 0;
+        self.stats.cure();
+        self.stats.clear_stun();
+
+        let xp_lost = self.xp - xp_for_level(self.level);
+        self.xp -= xp_lost;
+
+        self.status = CharacterStatus::Healthy;
+        self.refresh_status();
+        (health_lost, xp_lost)
+    }
+
+    pub fn retire(&mut self) {
+        self.status = CharacterStatus::Retired;
+    }
+
+    pub fn get_level(&self) -> u32 {
+        self.level
+    }
+
+    /// Adds the experience, growing the stats once for every level reached.
+    /// Returns the number of levels gained.
+    pub fn gain_xp(&mut self, amount: u32, rng: &mut RandomState) -> u32 {
+        self.xp += amount;
+
+        let mut gained = 0;
+        while self.xp >= xp_for_level(self.level + 1) {
+            self.level += 1;
+            self.stats.grow(rng);
+            gained += 1;
+        }
+        gained
+    }
 }
 
 
 
-pub fn create_character(id: String, name: String, rng: &mut RandomState) -> Result<(), String> {
+pub fn create_character(
+    id: String, name: String, class: Option<&CharacterClass>,
+    stats: CharacterStats, creation: Creation, inventory: Inventory
+) -> Result<(), ClrpgError> {
     write_character(
         &CharacterObj{ 
             id,
             name,
+            level: first_level(),
+            class: class.map(|c| c.name.clone()),
             status: CharacterStatus::Healthy,
             created: chrono::Local::now().naive_local(),
-            stats: CharacterStats::from_rng(rng)
+            xp: 0,
+            stats,
+            abilities: class.map(|c| c.abilities.clone()).unwrap_or_default(),
+            creation,
+            fatigue: 0,
+            inventory,
+            deepest: 0,
         }
     )
 }
 
-pub fn write_character(char: &CharacterObj) -> Result<(), String> {
+/// Writes the character, then records the pending transactions of their purse
+pub fn write_character(char: &CharacterObj) -> Result<(), ClrpgError> {
     let path = &format!("{}/{}", CHAR_FOLDER_NAME, char.name);
-    write_to_dir(&char, path)
+    write_to_dir(&char, path)?;
+    super::ledger::flush_purse(&char.name)
+}
+
+pub fn delete_character(name: &str) -> Result<(), ClrpgError> {
+    delete_file(&format!("{}/{}", CHAR_FOLDER_NAME, name))
+}
+
+/// Moves the character to the new name, carrying their history along
+pub fn rename_character(mut character: CharacterObj, name: &str) -> Result<CharacterObj, ClrpgError> {
+    let old = std::mem::replace(&mut character.name, name.to_string());
+    write_character(&character)?;
+    delete_character(&old)?;
+
+    let mut history = load_history();
+    let mut amended = false;
+    for record in history.iter_mut().filter(|r| r.character.as_deref() == Some(&old)) {
+        record.character = Some(name.to_string());
+        amended = true;
+    }
+    if amended {
+        write_history(&history)?;
+    }
+    super::ledger::rename_in_ledger(&old, name)?;
+
+    Ok(character)
+}
+
+/// A retired adventurer, remembered along with the runs they made
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HallEntry {
+    pub character: CharacterObj,
+    pub retired: chrono::NaiveDateTime,
+    pub runs: Vec<Run>,
+}
+
+impl HallEntry {
+    pub fn victories(&self) -> u32 {
+        self.runs.iter().map(|run| run.victories).sum()
+    }
+}
+
+/// Moves the character from the tavern into the hall of fame
+pub fn retire_character(mut character: CharacterObj) -> Result<HallEntry, ClrpgError> {
+    if !check_dir(HALL_FOLDER_NAME)? {
+        create_folder(HALL_FOLDER_NAME)?;
+    }
+    character.retire();
+
+    let entry = HallEntry {
+        runs: runs_of(&character.name, &load_history()),
+        retired: chrono::Local::now().naive_local(),
+        character,
+    };
+    write_to_dir(&entry, &format!("{}/{}", HALL_FOLDER_NAME, entry.character.id))?;
+    delete_character(&entry.character.name)?;
+
+    Ok(entry)
+}
+
+/// Reads every entry in the folder. Entries that cannot be read are
+/// reported as warnings and skipped.
+fn load_entries<T>(folder: &str) -> Vec<T>
+where
+    T: for<'a> Deserialize<'a> {
+    let mut loaded: Vec<T> = vec![];
+
+    if let Ok(entries) = std::fs::read_dir(folder) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match read_to_string(&path).map(|json| serde_json::from_str::<T>(&json)) {
+                Ok(Ok(entry)) => loaded.push(entry),
+                Ok(Err(err)) => warnln!("{:?} is corrupted: {}", path, err),
+                Err(err) => warnln!("Unable to read from {:?}: {}", path, err),
+            }
+        }
+    }
+    loaded
+}
+
+/// Reads the hall of fame, oldest retirement first
+pub fn load_hall() -> Vec<HallEntry> {
+    let mut hall: Vec<HallEntry> = load_entries(HALL_FOLDER_NAME);
+    hall.sort_by_key(|entry| entry.retired);
+    hall
+}
+
+/// Gold the temple asks to revive a character, before the level and faith
+/// are taken into account
+pub const REVIVAL_BASE_COST: u32 = 50;
+pub const REVIVAL_COST_PER_LEVEL: u32 = 25;
+/// Chance, in percent, of a revival taking hold without any faith
+pub const REVIVAL_BASE_CHANCE: u32 = 50;
+pub const REVIVAL_MAX_CHANCE: u32 = 95;
+
+/// A fallen adventurer, remembered along with how and where they died
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Grave {
+    pub character: CharacterObj,
+    pub died: chrono::NaiveDateTime,
+    pub cause: String,
+    /// Floor the character fell on, if they fell inside the dungeon
+    pub floor: Option<u32>,
+    pub killer: Option<String>,
+    pub runs: Vec<Run>,
+}
+
+/// Moves the dead character from the tavern into the graveyard
+pub fn bury_character(
+    character: CharacterObj, cause: String, floor: Option<u32>, killer: Option<String>
+) -> Result<Grave, ClrpgError> {
+    if !check_dir(GRAVEYARD_FOLDER_NAME)? {
+        create_folder(GRAVEYARD_FOLDER_NAME)?;
+    }
+
+    let grave = Grave {
+        runs: runs_of(&character.name, &load_history()),
+        died: chrono::Local::now().naive_local(),
+        cause,
+        floor,
+        killer,
+        character,
+    };
+    write_grave(&grave)?;
+    delete_character(&grave.character.name)?;
+
+    Ok(grave)
+}
+
+impl Grave {
+    /// Gold the temple asks for its rites, cheaper for the faithful
+    pub fn revival_cost(&self) -> u32 {
+        let base = REVIVAL_BASE_COST + REVIVAL_COST_PER_LEVEL * self.character.level;
+        let faith = self.character.stats.faith().clamp(0, 25) as u32;
+        base * (100 - 2 * faith) / 100
+    }
+
+    /// Chance, in percent, that the rites bring the character back
+    pub fn revival_chance(&self) -> u32 {
+        let faith = self.character.stats.faith().max(0) as u32;
+        (REVIVAL_BASE_CHANCE + 3 * faith).min(REVIVAL_MAX_CHANCE)
+    }
 }
 
+/// Writes the grave, then records the pending transactions of the purse
+/// buried with the character
+pub fn write_grave(grave: &Grave) -> Result<(), ClrpgError> {
+    write_to_dir(grave, &format!("{}/{}", GRAVEYARD_FOLDER_NAME, grave.character.id))?;
+    super::ledger::flush_purse(&grave.character.name)
+}
+
+/// Finds the most recent grave of the character
+pub fn find_grave(name: &str) -> Option<Grave> {
+    load_graveyard().into_iter().find(|grave| grave.character.name == name)
+}
+
+/// Moves the revived character out of their grave and back into the tavern
+pub fn unbury_character(grave: Grave) -> Result<CharacterObj, ClrpgError> {
+    write_character(&grave.character)?;
+    delete_file(&format!("{}/{}", GRAVEYARD_FOLDER_NAME, grave.character.id))?;
+    Ok(grave.character)
+}
 
-pub fn require_character(character: &str) -> Result<CharacterObj, String> {
+/// Reads the graveyard, most recent death first
+pub fn load_graveyard() -> Vec<Grave> {
+    let mut graveyard: Vec<Grave> = load_entries(GRAVEYARD_FOLDER_NAME);
+    graveyard.sort_by_key(|grave| std::cmp::Reverse(grave.died));
+    graveyard
+}
+
+
+pub fn require_character(character: &str) -> Result<CharacterObj, ClrpgError> {
     let path = &format!("{}/{}", CHAR_FOLDER_NAME, character);
     require_file(&path)
 }
 
-pub fn load_character<P>(path: &P) -> Result<CharacterObj, String> 
+/// Reads the named character, or the party leader if no name is given,
+/// along with the meta it was looked up in
+pub fn require_owner(name: Option<&str>) -> Result<(CharacterObj, Meta), ClrpgError> {
+    require_root()?;
+    let meta = require_meta()?;
+
+    let name = match name.or(meta.leader()) {
+        Some(name) => name.to_string(),
+        None => return Err(ClrpgError::NoAdventurer),
+    };
+    if !check_character(&name)? {
+        return Err(ClrpgError::MissingCharacter { name });
+    }
+
+    Ok((require_character(&name)?, meta))
+}
+
+/// Reads every member of the party, in the order they joined
+pub fn require_party(meta: &Meta) -> Result<Vec<CharacterObj>, ClrpgError> {
+    if meta.party().is_empty() {
+        return Err(ClrpgError::NoAdventurer);
+    }
+    meta.party().iter().map(|name| require_character(name)).collect()
+}
+
+/// Reads a character, reporting failures as warnings
+pub fn load_character<P>(path: &P) -> Result<CharacterObj, ClrpgError> 
 where 
     P: AsRef<Path> + Debug{
 
@@ -771,20 +1888,16 @@ where
                 Ok(meta) => {
                     Ok(meta)
                 },
-                Err(err) => Err(
-                    format!(
-                        "{:?} is corrupted: {}", 
-                        path, err.to_string()
-                    )
-                ),
+                Err(err) => {
+                    warnln!("{:?} is corrupted: {}", path, err);
+                    Err(ClrpgError::CorruptFile { path: format!("{:?}", path), resettable: true })
+                },
             }
         },
-        Err(err) => Err(
-            format!(
-                "Unable to read from {:?}: {}", 
-                path, err.to_string()
-            )
-        ),
+        Err(err) => {
+            warnln!("Unable to read from {:?}: {}", path, err);
+            Err(ClrpgError::io("read from", &format!("{:?}", path), err))
+        },
     }
 }
 
@@ -792,13 +1905,10 @@ pub fn load_characters() -> Vec<CharacterObj> {
     let mut characters: Vec<CharacterObj> = vec![];
 
     if let Ok(entries) = std::fs::read_dir(CHAR_FOLDER_NAME) {
-        for res in entries {
-            if let Ok(entry) = res {
-                // report failures but do not fail
-                match load_character(&entry.path()) {
-                    Ok(obj) => characters.push(obj),
-                    Err(err) => warnln!("{}", err.to_string()),
-                }
+        for entry in entries.flatten() {
+            // failures are reported by load_character but do not fail
+            if let Ok(obj) = load_character(&entry.path()) {
+                characters.push(obj);
             }
         }
     }
@@ -806,12 +1916,280 @@ pub fn load_characters() -> Vec<CharacterObj> {
 }
 
 
-pub fn delete_root() -> Result<(), String> {
+pub fn delete_root() -> Result<(), ClrpgError> {
     delete_dir(ROOT_FOLDER_NAME)
 }
 
-pub fn _delete_char() -> Result<(), String> {
+pub fn _delete_char() -> Result<(), ClrpgError> {
     delete_dir(CHAR_FOLDER_NAME)
 }
 
 
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Healthy character with the given stats that only lives in memory
+    pub(crate) fn test_character(name: &str, stats: CharacterStats) -> CharacterObj {
+        serde_json::from_value(serde_json::json!({
+            "id": "testtest",
+            "name": name,
+            "status": "Healthy",
+            "created": "2022-08-01T12:00:00",
+            "stats": stats,
+        })).unwrap()
+    }
+
+    const STATUSES: [MetaStatus; 4] = [
+        MetaStatus::HelpWanted, MetaStatus::OutsideTheDungeon, MetaStatus::InTheDungeon, MetaStatus::InCombat,
+    ];
+    const TRANSITIONS: [Transition; 7] = [
+        Transition::Wait, Transition::Enter, Transition::Explore, Transition::Engage,
+        Transition::Victory, Transition::Defeat, Transition::Flee,
+    ];
+
+    #[test]
+    fn transitions_follow_the_state_machine() {
+        use MetaStatus::*;
+        let allowed = [
+            (Transition::Wait, HelpWanted, OutsideTheDungeon),
+            (Transition::Wait, OutsideTheDungeon, OutsideTheDungeon),
+            (Transition::Enter, OutsideTheDungeon, InTheDungeon),
+            (Transition::Explore, InTheDungeon, InTheDungeon),
+            (Transition::Engage, InTheDungeon, InCombat),
+            (Transition::Victory, InCombat, InTheDungeon),
+            (Transition::Defeat, InCombat, HelpWanted),
+            (Transition::Flee, OutsideTheDungeon, HelpWanted),
+            (Transition::Flee, InTheDungeon, HelpWanted),
+            (Transition::Flee, InCombat, HelpWanted),
+        ];
+
+        for transition in TRANSITIONS {
+            for from in STATUSES {
+                let expected = allowed.iter()
+                    .find(|(t, f, _)| *t == transition && *f == from)
+                    .map(|(_, _, to)| *to);
+                assert_eq!(transition.target(from), expected, "{:?} from {:?}", transition, from);
+            }
+        }
+    }
+
+    #[test]
+    fn refused_transitions_leave_the_meta_alone() {
+        let mut meta = Meta::new(7);
+        meta.join("Ana").unwrap();
+
+        let err = meta.transition(Transition::Victory).unwrap_err();
+        assert_eq!(err, TransitionError {
+            transition: Transition::Victory,
+            from: MetaStatus::OutsideTheDungeon,
+            character: Some("Ana".to_string()),
+        });
+        assert_eq!(meta.status(), MetaStatus::OutsideTheDungeon);
+        assert_eq!(meta.party(), ["Ana"]);
+        assert_eq!(meta.pending.len(), 1);
+    }
+
+    #[test]
+    fn transitions_are_recorded_and_leaving_forgets_the_adventurer() {
+        let mut meta = Meta::new(7);
+        meta.join("Ana").unwrap();
+        for transition in [Transition::Enter, Transition::Engage, Transition::Victory] {
+            meta.transition(transition).unwrap();
+        }
+        meta.position = Some(Position { floor: 1, room: 3 });
+
+        assert_eq!(meta.transition(Transition::Flee), Ok(MetaStatus::InTheDungeon));
+        assert_eq!(meta.status(), MetaStatus::HelpWanted);
+        assert!(meta.party().is_empty());
+        assert_eq!(meta.position, None);
+
+        let taken = meta.pending.iter()
+            .map(|r| (r.transition, r.from, r.to, r.character.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(taken[0], (Transition::Wait, MetaStatus::HelpWanted, MetaStatus::OutsideTheDungeon, Some("Ana")));
+        assert_eq!(taken[4], (Transition::Flee, MetaStatus::InTheDungeon, MetaStatus::HelpWanted, Some("Ana")));
+    }
+    #[test]
+    fn parting_keeps_the_status_until_the_last_member_leaves() {
+        let mut meta = Meta::new(7);
+        meta.join("Ana").unwrap();
+        meta.join("Bo").unwrap();
+        meta.join("Cy").unwrap();
+        meta.transition(Transition::Enter).unwrap();
+        meta.transition(Transition::Engage).unwrap();
+
+        assert_eq!(meta.part("Bo", Transition::Defeat), Ok(MetaStatus::InCombat));
+        assert_eq!(meta.status(), MetaStatus::InCombat);
+        assert_eq!(meta.party(), ["Ana", "Cy"]);
+        assert_eq!(meta.leader(), Some("Ana"));
+
+        meta.part("Ana", Transition::Flee).unwrap();
+        assert_eq!(meta.status(), MetaStatus::InCombat);
+        assert_eq!(meta.party_name().as_deref(), Some("Cy"));
+
+        meta.part("Cy", Transition::Defeat).unwrap();
+        assert_eq!(meta.status(), MetaStatus::HelpWanted);
+        assert!(meta.party().is_empty());
+
+        // every member gets their own record, but only the last one moves the dungeon
+        let records = meta.pending.iter()
+            .filter(|r| matches!(r.transition, Transition::Defeat | Transition::Flee))
+            .map(|r| (r.character.as_deref().unwrap(), r.to))
+            .collect::<Vec<_>>();
+        assert_eq!(records, [
+            ("Bo", MetaStatus::InCombat), ("Ana", MetaStatus::InCombat), ("Cy", MetaStatus::HelpWanted),
+        ]);
+    }
+
+    #[test]
+    fn parting_is_refused_where_the_transition_is() {
+        let mut meta = Meta::new(7);
+        meta.join("Ana").unwrap();
+        meta.join("Bo").unwrap();
+
+        assert!(meta.part("Ana", Transition::Defeat).is_err());
+        assert_eq!(meta.party(), ["Ana", "Bo"]);
+
+        meta.part("Ana", Transition::Flee).unwrap();
+        assert_eq!(meta.status(), MetaStatus::OutsideTheDungeon);
+        assert_eq!(meta.party(), ["Bo"]);
+    }
+
+    #[test]
+    fn rerolled_sets_only_depend_on_the_seed_and_id() {
+        let roll = |state: &mut RandomState, seed: u64, id: &str| {
+            state.with_seed(Creation::reroll_seed(seed, id), |state| {
+                serde_json::to_value((0..3).map(|_| CharacterStats::from_rng(state)).collect::<Vec<_>>()).unwrap()
+            })
+        };
+
+        let sets = roll(&mut RandomState::seeded(1), 7, "abcdefgh");
+        assert_eq!(sets, roll(&mut RandomState::seeded(2), 7, "abcdefgh"));
+        assert_ne!(sets, roll(&mut RandomState::seeded(1), 7, "hgfedcba"));
+        assert_ne!(sets, roll(&mut RandomState::seeded(1), 8, "abcdefgh"));
+
+        // the shared stream carries on as if nothing was rolled
+        let mut state = RandomState::seeded(1);
+        roll(&mut state, 7, "abcdefgh");
+        assert_eq!(state.generate_id(), RandomState::seeded(1).generate_id());
+    }
+
+    #[test]
+    fn modifiers_tick_down_and_wear_off() {
+        let mut stats = CharacterStats::new(100, 10, 10, 10, 10, 10);
+        stats.apply(Stat::Power, Modifier { name: "Rage".to_string(), amount: 5, turns: 2 });
+        stats.apply(Stat::Health, Modifier { name: "Poison".to_string(), amount: -10, turns: 1 });
+        assert_eq!(stats.power(), 15);
+
+        let ticks = stats.tick("Ana", true);
+        assert_eq!(ticks.len(), 1);
+        assert_eq!((ticks[0].name.as_str(), ticks[0].amount, ticks[0].expired), ("Poison", -10, true));
+        assert_eq!(stats.modifiers().len(), 1);
+        assert_eq!(stats.modifiers()[0].1.turns, 1);
+        assert_eq!(stats.power(), 15);
+
+        let ticks = stats.tick("Ana", true);
+        assert_eq!((ticks[0].name.as_str(), ticks[0].amount, ticks[0].expired), ("Rage", 0, true));
+        assert!(stats.modifiers().is_empty());
+        assert_eq!(stats.power(), 10);
+    }
+
+    #[test]
+    fn lasting_modifiers_never_wear_off() {
+        let mut stats = CharacterStats::new(100, 10, 10, 10, 10, 10);
+        stats.apply(Stat::Block, Modifier { name: "Buckler".to_string(), amount: 3, turns: Modifier::LASTING });
+        stats.apply(Stat::Block, Modifier { name: "Stone Skin".to_string(), amount: 2, turns: 1 });
+
+        let ticks = stats.tick("Ana", true);
+        assert_eq!(ticks.len(), 1);
+        assert_eq!(ticks[0].name, "Stone Skin");
+        for _ in 0..10 {
+            assert!(stats.tick("Ana", true).is_empty());
+        }
+        assert_eq!(stats.block(), 13);
+        assert_eq!(stats.modifiers()[0].1.turns, Modifier::LASTING);
+    }
+
+    #[test]
+    fn poison_outside_of_combat_leaves_some_health() {
+        let mut stats = CharacterStats::new(5, 10, 10, 10, 10, 10);
+        stats.apply(Stat::Health, Modifier { name: "Poison".to_string(), amount: -10, turns: 3 });
+
+        assert_eq!(stats.tick("Ana", false)[0].amount, -4);
+        assert!(stats.tick("Ana", false).is_empty());
+        assert_eq!(stats.tick("Ana", true)[0].amount, -1);
+    }
+
+    #[test]
+    fn damage_is_absorbed_before_health_and_stops_at_zero() {
+        let mut health = HealthStat::new(100);
+        assert_eq!(health.shield(10), 10);
+
+        assert_eq!(health.take_damage(15), 15);
+        assert_eq!((health.curr, health.temp), (95, 0));
+
+        assert_eq!(health.take_damage(500), 95);
+        assert_eq!(health.curr, 0);
+        assert!(health.is_depleted());
+
+        assert_eq!(health.take_damage(-5), 0);
+        assert_eq!(health.curr, 0);
+    }
+
+    #[test]
+    fn shields_stop_at_the_cap() {
+        let mut health = HealthStat::new(100);
+        assert_eq!(health.shield(20), 20);
+        assert_eq!(health.shield(20), 5);
+        assert_eq!(health.temp, 100 * TEMP_HEALTH_PERCENT / 100);
+        assert_eq!(health.shield(-10), 0);
+        assert_eq!(health.temp, 25);
+    }
+
+    #[test]
+    fn overhealing_spills_into_temporary_health() {
+        let mut health = HealthStat::new(100);
+        health.take_damage(10);
+
+        assert_eq!(health.overheal(15), (10, 5));
+        assert_eq!(health.overheal(100), (0, 20));
+        assert_eq!((health.curr, health.temp), (100, 25));
+    }
+
+    fn grave(level: u32, faith: i32) -> Grave {
+        let mut character = test_character("Ana", CharacterStats::new(100, 10, 10, 10, faith, 10));
+        character.level = level;
+        Grave {
+            character,
+            died: chrono::NaiveDateTime::default(),
+            cause: "a test".to_string(),
+            floor: Some(1),
+            killer: None,
+            runs: vec![],
+        }
+    }
+
+    #[test]
+    fn revivals_cost_more_with_levels_and_less_with_faith() {
+        assert_eq!(grave(1, 0).revival_cost(), REVIVAL_BASE_COST + REVIVAL_COST_PER_LEVEL);
+        assert_eq!(grave(3, 0).revival_cost(), REVIVAL_BASE_COST + 3 * REVIVAL_COST_PER_LEVEL);
+        assert_eq!(grave(1, 10).revival_cost(), 60);
+
+        // faith only takes up to half of the cost off, and never adds to it
+        assert_eq!(grave(1, 25).revival_cost(), 37);
+        assert_eq!(grave(1, 40).revival_cost(), 37);
+        assert_eq!(grave(1, -5).revival_cost(), 75);
+    }
+
+    #[test]
+    fn revival_chances_grow_with_faith_up_to_the_cap() {
+        assert_eq!(grave(1, 0).revival_chance(), REVIVAL_BASE_CHANCE);
+        assert_eq!(grave(1, -5).revival_chance(), REVIVAL_BASE_CHANCE);
+        assert_eq!(grave(1, 10).revival_chance(), 80);
+        assert_eq!(grave(1, 15).revival_chance(), REVIVAL_MAX_CHANCE);
+        assert_eq!(grave(1, 19).revival_chance(), REVIVAL_MAX_CHANCE);
+        assert_eq!(grave(9, 19).revival_chance(), REVIVAL_MAX_CHANCE);
+    }
+}
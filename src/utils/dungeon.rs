@@ -0,0 +1,411 @@
+use std::collections::VecDeque;
+
+use clap::ArgEnum;
+use colored::Colorize;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{common::*, error::ClrpgError};
+
+/// Health recovered by resting at the campfire of a rest room
+pub const CAMPFIRE_HEAL: i32 = 25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ArgEnum)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::East,
+        Direction::South,
+        Direction::West,
+    ];
+
+    fn offset(&self) -> (i32, i32) {
+        match *self {
+            Direction::North => (0, -1),
+            Direction::South => (0, 1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+        }
+    }
+}
+
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Direction::North => write!(f, "north"),
+            Direction::South => write!(f, "south"),
+            Direction::East => write!(f, "east"),
+            Direction::West => write!(f, "west"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum RoomKind {
+    Entrance,
+    Empty,
+    Combat,
+    Treasure,
+    Rest,
+    Shop,
+    Boss,
+}
+
+impl RoomKind {
+    /// Relative odds of a regular room being of the kind
+    fn weight(&self) -> u32 {
+        match *self {
+            RoomKind::Combat => 50,
+            RoomKind::Empty => 20,
+            RoomKind::Treasure => 15,
+            RoomKind::Rest => 10,
+            RoomKind::Shop => 5,
+            RoomKind::Entrance | RoomKind::Boss => 0,
+        }
+    }
+
+    fn describe(&self, cleared: bool) -> &'static str {
+        match (*self, cleared) {
+            (RoomKind::Entrance, _) => "Daylight trickles in through the dungeon door behind you.",
+            (RoomKind::Empty, _) => "The room is bare, save for dust and the odd cobweb.",
+            (RoomKind::Combat, false) => "Something shifts in the shadows. You are not alone here.",
+            (RoomKind::Combat, true) => "The remains of a recent struggle litter the floor.",
+            (RoomKind::Treasure, false) => "A heavy chest sits in the middle of the room, its lock glinting.",
+            (RoomKind::Treasure, true) => "An empty chest lies open in the middle of the room.",
+            (RoomKind::Rest, false) => "A small campfire crackles against the wall. It looks safe to rest here.",
+            (RoomKind::Rest, true) => "A campfire has burned down to embers against the wall.",
+            (RoomKind::Shop, _) => "A hooded merchant sits behind a blanket of curious wares, ready to trade.",
+            (RoomKind::Boss, false) => "A terrible presence fills the room. The master of this floor awaits.",
+            (RoomKind::Boss, true) => "The lair of the fallen master lies silent.",
+        }
+    }
+
+    /// Single letter used to draw the room on the map
+    fn symbol(&self) -> char {
+        match *self {
+            RoomKind::Entrance => 'E',
+            RoomKind::Empty => '.',
+            RoomKind::Combat => 'C',
+            RoomKind::Treasure => 'T',
+            RoomKind::Rest => 'R',
+            RoomKind::Shop => 'S',
+            RoomKind::Boss => 'B',
+        }
+    }
+}
+
+impl std::fmt::Display for RoomKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            RoomKind::Entrance => write!(f, "{}", "Entrance".green()),
+            RoomKind::Empty => write!(f, "{}", "Empty".black()),
+            RoomKind::Combat => write!(f, "{}", "Combat".red()),
+            RoomKind::Treasure => write!(f, "{}", "Treasure".yellow()),
+            RoomKind::Rest => write!(f, "{}", "Rest".cyan()),
+            RoomKind::Shop => write!(f, "{}", "Shop".magenta()),
+            RoomKind::Boss => write!(f, "{}", "Boss".red().bold()),
+        }
+    }
+}
+
+/// Experience earned for stepping into a room for the first time
+pub const EXPLORE_XP: u32 = 5;
+/// Depth of the last floor; felling its boss clears the dungeon
+pub const FINAL_FLOOR: u32 = 5;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Room {
+    pub id: usize,
+    pub x: i32,
+    pub y: i32,
+    pub kind: RoomKind,
+    pub visited: bool,
+    pub cleared: bool,
+}
+
+impl Room {
+    /// Whether a monster still lurks in the room
+    pub fn is_occupied(&self) -> bool {
+        matches!(self.kind, RoomKind::Combat | RoomKind::Boss) && !self.cleared
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Corridor {
+    pub from: usize,
+    pub to: usize,
+}
+
+/// A single floor of the dungeon, laid out on a grid of rooms joined by
+/// corridors between horizontally or vertically adjacent rooms
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Floor {
+    pub depth: u32,
+    pub rooms: Vec<Room>,
+    pub corridors: Vec<Corridor>,
+    pub entrance: usize,
+}
+
+impl Floor {
+    /// Generates the floor purely from the dungeon seed and the depth,
+    /// so the same seed always produces the same layout.
+    pub fn generate(seed: u64, depth: u32) -> Floor {
+        let mut rng = Prng::seed_from_u64(
+            seed.wrapping_add(depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        );
+        let room_count = 6 + 2 * depth as usize;
+
+        let mut rooms = vec![Room { id: 0, x: 0, y: 0, kind: RoomKind::Entrance, visited: false, cleared: true }];
+        let mut corridors = vec![];
+
+        // grow the floor one room at a time off a random existing room
+        while rooms.len() < room_count {
+            let from = rng.gen_range(0..rooms.len());
+            let dir = *Direction::ALL.choose(&mut rng).unwrap();
+            let (dx, dy) = dir.offset();
+            let (x, y) = (rooms[from].x + dx, rooms[from].y + dy);
+
+            if rooms.iter().any(|r| r.x == x && r.y == y) {
+                continue;
+            }
+
+            let id = rooms.len();
+            rooms.push(Room { id, x, y, kind: RoomKind::Empty, visited: false, cleared: false });
+            corridors.push(Corridor { from, to: id });
+        }
+
+        // add a few extra corridors so the floor is not a pure tree
+        for _ in 0..depth {
+            let a = rng.gen_range(0..rooms.len());
+            let dir = *Direction::ALL.choose(&mut rng).unwrap();
+            let (dx, dy) = dir.offset();
+            if let Some(b) = rooms.iter().position(|r| r.x == rooms[a].x + dx && r.y == rooms[a].y + dy) {
+                if !corridors.iter().any(|c| (c.from == a && c.to == b) || (c.from == b && c.to == a)) {
+                    corridors.push(Corridor { from: a, to: b });
+                }
+            }
+        }
+
+        let mut floor = Floor { depth, rooms, corridors, entrance: 0 };
+
+        // the boss waits in the room furthest away from the entrance
+        let distances = floor.distances_from(floor.entrance);
+        let boss = (0..floor.rooms.len())
+            .max_by_key(|&i| (distances[i], i))
+            .unwrap();
+
+        let kinds = [
+            RoomKind::Combat,
+            RoomKind::Empty,
+            RoomKind::Treasure,
+            RoomKind::Rest,
+            RoomKind::Shop
+        ];
+        for room in floor.rooms.iter_mut() {
+            if room.id == floor.entrance {
+                continue;
+            }
+            room.kind = if room.id == boss {
+                RoomKind::Boss
+            } else {
+                *kinds.choose_weighted(&mut rng, |k| k.weight()).unwrap()
+            };
+            room.cleared = room.kind == RoomKind::Empty;
+        }
+        floor.rooms[floor.entrance].visited = true;
+
+        floor
+    }
+
+    /// Breadth-first distances, in corridors, from the given room
+    fn distances_from(&self, start: usize) -> Vec<usize> {
+        let mut distances = vec![usize::MAX; self.rooms.len()];
+        let mut queue = VecDeque::from([start]);
+        distances[start] = 0;
+
+        while let Some(curr) = queue.pop_front() {
+            for dir in Direction::ALL {
+                if let Some(next) = self.neighbour(curr, dir) {
+                    if distances[next] == usize::MAX {
+                        distances[next] = distances[curr] + 1;
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+        distances
+    }
+
+    /// The room reached by taking the corridor in the given direction, if any
+    pub fn neighbour(&self, room: usize, dir: Direction) -> Option<usize> {
+        let (dx, dy) = dir.offset();
+        let (x, y) = (self.rooms[room].x + dx, self.rooms[room].y + dy);
+        let next = self.rooms.iter().position(|r| r.x == x && r.y == y)?;
+
+        self.corridors.iter()
+            .any(|c| (c.from == room && c.to == next) || (c.from == next && c.to == room))
+            .then_some(next)
+    }
+
+    pub fn room(&self, room: usize) -> &Room {
+        &self.rooms[room]
+    }
+
+    /// Describes the room. Below the first floor the entrance is a stairway
+    /// from above, and a fallen boss leaves the stairs further down open.
+    pub fn describe(&self, room: usize) -> &'static str {
+        let room = &self.rooms[room];
+        match room.kind {
+            RoomKind::Entrance if self.depth > 1 => "A stairway winds back up into the darkness behind you.",
+            RoomKind::Boss if room.cleared && self.depth < FINAL_FLOOR => {
+                "The lair of the fallen master lies silent. Behind it, stairs lead further down."
+            },
+            kind => kind.describe(room.cleared),
+        }
+    }
+
+    /// Directions with a corridor leading out of the room
+    pub fn exits(&self, room: usize) -> Vec<Direction> {
+        Direction::ALL.into_iter()
+            .filter(|&dir| self.neighbour(room, dir).is_some())
+            .collect()
+    }
+
+    /// Draws the explored part of the floor. Visited rooms show their kind,
+    /// rooms next to a visited one show as unknown, the rest stay hidden.
+    pub fn render_map(&self, current: Option<usize>) -> String {
+        let known: Vec<bool> = self.rooms.iter()
+            .map(|r| {
+                r.visited || Direction::ALL.into_iter().any(|dir| {
+                    self.neighbour(r.id, dir).is_some_and(|n| self.rooms[n].visited)
+                })
+            })
+            .collect();
+
+        let shown = || self.rooms.iter().filter(|r| known[r.id]);
+        let min_x = shown().map(|r| r.x).min().unwrap_or(0);
+        let max_x = shown().map(|r| r.x).max().unwrap_or(0);
+        let min_y = shown().map(|r| r.y).min().unwrap_or(0);
+        let max_y = shown().map(|r| r.y).max().unwrap_or(0);
+
+        let find = |x: i32, y: i32| {
+            self.rooms.iter().position(|r| r.x == x && r.y == y).filter(|&i| known[i])
+        };
+
+        let mut map = String::new();
+        for y in min_y..=max_y {
+            let mut rooms_line = String::new();
+            let mut corridor_line = String::new();
+
+            for x in min_x..=max_x {
+                match find(x, y) {
+                    Some(i) => {
+                        let room = &self.rooms[i];
+                        let cell = if Some(i) == current {
+                            "@".green().bold().to_string()
+                        } else if !room.visited {
+                            "?".black().to_string()
+                        } else if room.is_occupied() {
+                            room.kind.symbol().to_string().red().to_string()
+                        } else {
+                            room.kind.symbol().to_string()
+                        };
+                        rooms_line.push_str(&format!("[{}]", cell));
+
+                        let east = self.neighbour(i, Direction::East).filter(|&n| known[n]);
+                        rooms_line.push(if east.is_some() { '-' } else { ' ' });
+
+                        let south = self.neighbour(i, Direction::South).filter(|&n| known[n]);
+                        corridor_line.push_str(if south.is_some() { " |  " } else { "    " });
+                    },
+                    None => {
+                        rooms_line.push_str("    ");
+                        corridor_line.push_str("    ");
+                    }
+                }
+            }
+
+            map.push_str(rooms_line.trim_end());
+            map.push('\n');
+            if y < max_y {
+                map.push_str(corridor_line.trim_end());
+                map.push('\n');
+            }
+        }
+        map
+    }
+
+    pub fn entrance_position(&self) -> Position {
+        Position { floor: self.depth, room: self.entrance }
+    }
+}
+
+fn floor_path(depth: u32) -> String {
+    format!("{}/{}", FLOORS_FOLDER_NAME, depth)
+}
+
+pub fn create_floors(seed: u64) -> Result<(), ClrpgError> {
+    create_folder(FLOORS_FOLDER_NAME)?;
+    write_floor(&Floor::generate(seed, 1))
+}
+
+pub fn write_floor(floor: &Floor) -> Result<(), ClrpgError> {
+    write_to_dir(&floor, &floor_path(floor.depth))
+}
+
+/// Reads the floor, generating it from the seed if it has not been visited yet
+pub fn require_floor(meta: &Meta, depth: u32) -> Result<Floor, ClrpgError> {
+    if !check_dir(&floor_path(depth))? {
+        if !check_dir(FLOORS_FOLDER_NAME)? {
+            create_folder(FLOORS_FOLDER_NAME)?;
+        }
+        let floor = Floor::generate(meta.seed, depth);
+        write_floor(&floor)?;
+        return Ok(floor);
+    }
+    require_file(&floor_path(depth))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(floor: &Floor) -> String {
+        serde_json::to_string(floor).unwrap()
+    }
+
+    #[test]
+    fn floors_are_generated_from_the_seed_and_depth() {
+        for depth in 1..=5 {
+            assert_eq!(layout(&Floor::generate(7, depth)), layout(&Floor::generate(7, depth)));
+        }
+
+        assert_ne!(layout(&Floor::generate(7, 1)), layout(&Floor::generate(8, 1)));
+        assert_ne!(layout(&Floor::generate(7, 1)), layout(&Floor::generate(7, 2)));
+    }
+
+    #[test]
+    fn every_floor_has_an_entrance_and_a_boss() {
+        for depth in 1..=5 {
+            let floor = Floor::generate(7, depth);
+            assert_eq!(floor.depth, depth);
+            assert_eq!(floor.room(floor.entrance).kind, RoomKind::Entrance);
+            assert_eq!(floor.rooms.iter().filter(|room| room.kind == RoomKind::Boss).count(), 1);
+        }
+    }
+
+    #[test]
+    fn every_room_can_be_reached_from_the_entrance() {
+        for seed in 0..20 {
+            let floor = Floor::generate(seed, 3);
+            assert!(floor.distances_from(floor.entrance).iter().all(|&d| d != usize::MAX));
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use colored::Colorize;
+
+use super::common::{CharacterStatus, TransitionError, PARTY_SIZE};
+use super::dungeon::Direction;
+
+#[derive(Debug)]
+pub enum ClrpgError {
+    /// There is no `.dungeon` in the current directory
+    MissingRoot,
+    /// There already is a `.dungeon` in the current directory
+    RootExists,
+    /// A required file is missing or cannot be parsed. The dungeon can be
+    /// reset with its current seed as long as the meta is still readable.
+    CorruptFile { path: String, resettable: bool },
+    /// Reading or writing a file failed for reasons other than corruption
+    Io { action: &'static str, path: String, source: std::io::Error },
+    MissingCharacter { name: String },
+    CharacterExists { name: String },
+    DeadCharacter { name: String },
+    /// No one of the name lies in the graveyard
+    MissingGrave { name: String },
+    /// No built-in or custom class goes by the name
+    UnknownClass { name: String },
+    /// A choice made at creation is out of bounds or was never given
+    InvalidChoice(String),
+    /// The character does not carry or wear the item
+    MissingItem { name: String, item: String },
+    /// The item cannot be used in the way asked for, like equipping a potion
+    WrongItem { item: String, action: &'static str },
+    /// The character cannot pay for something
+    NotEnoughGold { name: String, cost: u32, gold: u32 },
+    /// The character is too worn out to enter the dungeon
+    Unfit { name: String, status: CharacterStatus },
+    /// The character is the one currently attempting the dungeon
+    CharacterBusy { name: String },
+    /// The character is not one of the party attempting the dungeon
+    NotInParty { name: String },
+    /// The party already has as many members as it can take
+    PartyFull,
+    /// No adventurer is waiting at or inside the dungeon
+    NoAdventurer,
+    /// The command is not allowed in the current `MetaStatus`
+    InvalidState(TransitionError),
+    NoPassage(Direction),
+    /// There is no closed treasure chest in the current room
+    NoChest,
+    /// There are no open stairs down in the current room
+    NoStairs,
+    Unexpected(String),
+}
+
+impl ClrpgError {
+    pub fn io(action: &'static str, path: &(impl std::fmt::Display + ?Sized), source: std::io::Error) -> Self {
+        ClrpgError::Io { action, path: path.to_string(), source }
+    }
+
+    /// Suggestion on how to get out of the error, if there is one
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            ClrpgError::MissingRoot => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "init".black(),
+                    "\" to create the dungeon)".white()
+                )
+            ),
+            ClrpgError::RootExists => Some(
+                format!("{}{} {} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "init".black(),
+                    "--force".black(),
+                    "\" to replace the dungeon)".white()
+                )
+            ),
+            ClrpgError::CorruptFile { resettable: true, .. } => Some(
+                format!("{}{} {} {}{}\n   {}{} {} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "init".black(),
+                    "--reset".black(),
+                    "\" to create a new dungeon with the current seed); or".white(),
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "init".black(),
+                    "--force".black(),
+                    "\" to create a new dungeon)".white()
+                )
+            ),
+            ClrpgError::CorruptFile { resettable: false, .. } => Some(
+                format!("{}{} {} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "init".black(),
+                    "--force".black(),
+                    "\" to create a new dungeon)".white()
+                )
+            ),
+            ClrpgError::MissingCharacter { .. } => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "list".black(),
+                    "\" to see who is in the tavern)".white()
+                )
+            ),
+            ClrpgError::MissingGrave { .. } => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "graveyard".black(),
+                    "\" to see who has fallen)".white()
+                )
+            ),
+            ClrpgError::NoAdventurer => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "character wait <NAME>".black(),
+                    "\" to wait an adventurer)".white()
+                )
+            ),
+            ClrpgError::UnknownClass { .. } => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "character classes".black(),
+                    "\" to see the available classes)".white()
+                )
+            ),
+            ClrpgError::MissingItem { .. } => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "inventory".black(),
+                    "\" to see what they carry)".white()
+                )
+            ),
+            ClrpgError::Unfit { .. } => Some(
+                format!("{}{} {}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "run".black(),
+                    "\" and \"".white(),
+                    "clrpg tavern rest <NAME>".black(),
+                    "\" to let them recover)".white()
+                )
+            ),
+            ClrpgError::CharacterBusy { .. } => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "run".black(),
+                    "\" to call them back to the tavern)".white()
+                )
+            ),
+            ClrpgError::NotInParty { .. } | ClrpgError::PartyFull => Some(
+                format!("{}{} {}{}",
+                    "(use \"".white(),
+                    "clrpg".yellow(),
+                    "status".black(),
+                    "\" to see who is in the party)".white()
+                )
+            ),
+            ClrpgError::InvalidState(err) => err.hint(),
+            _ => None,
+        }
+    }
+
+    /// Process exit code for the failure class of the error, so scripts
+    /// can branch on it. 2 is left to clap for usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ClrpgError::Io { .. } | ClrpgError::Unexpected(_) => 1,
+            ClrpgError::UnknownClass { .. }
+            | ClrpgError::InvalidChoice(_)
+            | ClrpgError::WrongItem { .. } => 2,
+            ClrpgError::MissingRoot => 3,
+            ClrpgError::CorruptFile { .. } => 4,
+            ClrpgError::MissingCharacter { .. }
+            | ClrpgError::MissingGrave { .. }
+            | ClrpgError::MissingItem { .. } => 5,
+            ClrpgError::NoAdventurer
+            | ClrpgError::CharacterBusy { .. }
+            | ClrpgError::NotInParty { .. }
+            | ClrpgError::InvalidState(_) => 6,
+            ClrpgError::DeadCharacter { .. } => 7,
+            ClrpgError::RootExists
+            | ClrpgError::CharacterExists { .. }
+            | ClrpgError::NoPassage(_)
+            | ClrpgError::NoChest
+            | ClrpgError::NoStairs
+            | ClrpgError::PartyFull
+            | ClrpgError::NotEnoughGold { .. }
+            | ClrpgError::Unfit { .. } => 8,
+        }
+    }
+
+    /// The error for `--format json`, with the hint and exit code alongside
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": self.to_string(),
+            "hint": self.hint(),
+            "exit_code": self.exit_code(),
+        })
+    }
+
+    /// The error as shown to the player, followed by its hint
+    pub fn render(&self) -> String {
+        match self.hint() {
+            Some(hint) => format!("{}\n   {}", self.to_string().red(), hint),
+            None => self.to_string().red().to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ClrpgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ClrpgError::MissingRoot => write!(f, "There is no dungeon!"),
+            ClrpgError::RootExists => write!(f, "There already is a dungeon here!"),
+            ClrpgError::CorruptFile { path, .. } => write!(f, "The dungeon is corrupted! {} is damaged.", path),
+            ClrpgError::Io { action, path, source } => write!(f, "Unable to {} {}: {}", action, path, source),
+            ClrpgError::MissingCharacter { name } => write!(f, "The adventurer {} is not in the tavern.", name.bold()),
+            ClrpgError::CharacterExists { name } => write!(f, "Character {} already exist!", name.yellow().bold()),
+            ClrpgError::DeadCharacter { name } => write!(f, "{} is dead!", name.bold()),
+            ClrpgError::MissingGrave { name } => write!(f, "There is no grave of {}.", name.bold()),
+            ClrpgError::UnknownClass { name } => write!(f, "There is no {} class.", name.bold()),
+            ClrpgError::InvalidChoice(reason) => write!(f, "{}", reason),
+            ClrpgError::MissingItem { name, item } => write!(f, "{} has no {}.", name.bold(), item.bold()),
+            ClrpgError::WrongItem { item, action } => write!(f, "The {} cannot be {}.", item.bold(), action),
+            ClrpgError::Unfit { name, status } => write!(
+                f, "{} is {} and in no shape to enter the dungeon.",
+                name.bold(), format!("{:?}", status).to_lowercase()
+            ),
+            ClrpgError::CharacterBusy { name } => write!(f, "{} is out attempting the dungeon!", name.bold()),
+            ClrpgError::NotInParty { name } => write!(f, "{} is not in the party.", name.bold()),
+            ClrpgError::PartyFull => write!(f, "The party already has {} members.", PARTY_SIZE),
+            ClrpgError::NoAdventurer => write!(f, "No one is in the dungeon right now!"),
+            ClrpgError::InvalidState(err) => write!(f, "{}", err),
+            ClrpgError::NoPassage(direction) => write!(f, "There is no passage leading {}.", direction),
+            ClrpgError::NotEnoughGold { name, cost, gold } => write!(
+                f, "{} has only {} gold, but it costs {}.", name.bold(), gold, cost
+            ),
+            ClrpgError::NoChest => write!(f, "There is no unopened chest here."),
+            ClrpgError::NoStairs => write!(f, "There are no open stairs leading down here."),
+            ClrpgError::Unexpected(reason) => write!(f, "Unexpected error occured: {}", reason),
+        }
+    }
+}
+
+impl From<TransitionError> for ClrpgError {
+    fn from(err: TransitionError) -> Self {
+        ClrpgError::InvalidState(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::common::*;
+
+    #[test]
+    fn refused_transitions_are_invalid_states() {
+        let mut meta = Meta::new(7);
+        let err: ClrpgError = meta.transition(Transition::Enter).unwrap_err().into();
+
+        assert!(matches!(
+            err,
+            ClrpgError::InvalidState(TransitionError { transition: Transition::Enter, from: MetaStatus::HelpWanted, .. })
+        ));
+        assert!(err.hint().is_some());
+    }
+
+    #[test]
+    fn characters_cannot_be_read_without_a_dungeon() {
+        // tests run from the crate root, which holds no dungeon
+        let err = require_character("Nobody").unwrap_err();
+        assert!(
+            matches!(&err, ClrpgError::CorruptFile { path, resettable: false } if path == META_FILE_NAME),
+            "{:?}", err
+        );
+    }
+
+    #[test]
+    fn rendering_adds_the_hint() {
+        let err = ClrpgError::MissingCharacter { name: "Ana".to_string() };
+        assert!(matches!(&err, ClrpgError::MissingCharacter { name } if name == "Ana"));
+        assert_eq!(err.render().lines().count(), 2);
+        assert_eq!(ClrpgError::RootExists.render().lines().count(), 2);
+        assert_eq!(ClrpgError::Unexpected("oops".to_string()).render().lines().count(), 1);
+    }
+
+    #[test]
+    fn exit_codes_follow_the_documented_table() {
+        let transition = Meta::new(7).check(Transition::Enter).unwrap_err();
+        let io = std::io::Error::other("disk on fire");
+        let cases = [
+            (ClrpgError::io("read from", META_FILE_NAME, io), 1),
+            (ClrpgError::Unexpected("oops".to_string()), 1),
+            (ClrpgError::UnknownClass { name: "Bard".to_string() }, 2),
+            (ClrpgError::InvalidChoice("No set was picked.".to_string()), 2),
+            (ClrpgError::WrongItem { item: "Torch".to_string(), action: "equipped" }, 2),
+            (ClrpgError::MissingRoot, 3),
+            (ClrpgError::CorruptFile { path: META_FILE_NAME.to_string(), resettable: false }, 4),
+            (ClrpgError::MissingCharacter { name: "Ana".to_string() }, 5),
+            (ClrpgError::MissingItem { name: "Ana".to_string(), item: "Torch".to_string() }, 5),
+            (ClrpgError::MissingGrave { name: "Ana".to_string() }, 5),
+            (ClrpgError::NoAdventurer, 6),
+            (ClrpgError::CharacterBusy { name: "Ana".to_string() }, 6),
+            (ClrpgError::NotInParty { name: "Ana".to_string() }, 6),
+            (ClrpgError::InvalidState(transition), 6),
+            (ClrpgError::DeadCharacter { name: "Ana".to_string() }, 7),
+            (ClrpgError::RootExists, 8),
+            (ClrpgError::CharacterExists { name: "Ana".to_string() }, 8),
+            (ClrpgError::NoPassage(Direction::North), 8),
+            (ClrpgError::NoChest, 8),
+            (ClrpgError::NoStairs, 8),
+            (ClrpgError::PartyFull, 8),
+            (ClrpgError::NotEnoughGold { name: "Ana".to_string(), cost: 10, gold: 3 }, 8),
+            (ClrpgError::Unfit { name: "Ana".to_string(), status: CharacterStatus::Wounded }, 8),
+        ];
+
+        for (err, code) in cases {
+            assert_eq!(err.exit_code(), code, "{:?}", err);
+            assert!(
+                crate::EXIT_CODES.lines().any(|line| line.trim_start().starts_with(&format!("{} ", code))),
+                "exit code {} is not documented", code
+            );
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use colored::{ColoredString, Colorize};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::common::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ItemKind {
+    Consumable,
+    Weapon,
+    Armor,
+    Trinket,
+    /// Only good for selling
+    Valuable,
+}
+
+impl ItemKind {
+    /// Whether the item goes into an equipment slot
+    pub fn is_equipment(&self) -> bool {
+        matches!(self, ItemKind::Weapon | ItemKind::Armor | ItemKind::Trinket)
+    }
+}
+
+impl std::fmt::Display for ItemKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            ItemKind::Consumable => write!(f, "CONSUMABLE"),
+            ItemKind::Weapon => write!(f, "WEAPON"),
+            ItemKind::Armor => write!(f, "ARMOR"),
+            ItemKind::Trinket => write!(f, "TRINKET"),
+            ItemKind::Valuable => write!(f, "VALUABLE"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Epic,
+    Legendary,
+}
+
+impl Rarity {
+    const ALL: [Rarity; 5] = [
+        Rarity::Common,
+        Rarity::Uncommon,
+        Rarity::Rare,
+        Rarity::Epic,
+        Rarity::Legendary,
+    ];
+
+    /// Relative odds of a treasure being of the rarity. Deeper floors hold
+    /// rarer treasure.
+    fn weight(&self, depth: u32) -> u32 {
+        match *self {
+            Rarity::Common => 60,
+            Rarity::Uncommon => 25 + 5 * depth,
+            Rarity::Rare => 10 + 3 * depth,
+            Rarity::Epic => 4 + 2 * depth,
+            Rarity::Legendary => 1 + depth,
+        }
+    }
+
+    /// Colors the text the way items of the rarity are shown
+    fn paint(&self, text: &str) -> ColoredString {
+        match *self {
+            Rarity::Common => text.normal(),
+            Rarity::Uncommon => text.green(),
+            Rarity::Rare => text.blue(),
+            Rarity::Epic => text.magenta(),
+            Rarity::Legendary => text.yellow().bold(),
+        }
+    }
+}
+
+impl std::fmt::Display for Rarity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let label = format!("{:?}", self).to_uppercase();
+        write!(f, "{}", self.paint(&label))
+    }
+}
+
+/// What a consumable does when used
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum ItemEffect {
+    /// Restores health up to the maximum
+    Heal(i32),
+    /// Gives temporary health
+    Shield(i32),
+    /// Purges poison
+    Cure,
+    /// Puts a status effect on the user
+    Bless { stat: Stat, amount: i32, turns: i32 },
+    /// Stands in for the temple's fee when reviving the fallen, adding to
+    /// the chance, in percent, of the revival taking hold
+    Revive(u32),
+}
+
+impl ItemEffect {
+    /// Applies the effect and describes what it did
+    pub fn apply(&self, item: &str, stats: &mut CharacterStats) -> String {
+        match *self {
+            ItemEffect::Heal(amount) => format!("recovers {} health", stats.health_mut().heal(amount)),
+            ItemEffect::Shield(amount) => format!("gains {} temporary health", stats.health_mut().shield(amount)),
+            ItemEffect::Cure if stats.health().is_poisoned() => {
+                stats.cure();
+                "is cured of poison".to_string()
+            },
+            ItemEffect::Cure | ItemEffect::Revive(_) => "feels no different".to_string(),
+            ItemEffect::Bless { stat, amount, turns } => {
+                stats.apply(stat, Modifier { name: item.to_string(), amount, turns });
+                format!("gets {:+} {} for {} turns", amount, stat, turns)
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ItemEffect {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ItemEffect::Heal(amount) => write!(f, "heals {}", amount),
+            ItemEffect::Shield(amount) => write!(f, "{} temporary health", amount),
+            ItemEffect::Cure => write!(f, "cures poison"),
+            ItemEffect::Bless { stat, amount, turns } => write!(f, "{} {:+} for {} turns", stat, amount, turns),
+            ItemEffect::Revive(bonus) => write!(f, "revives the fallen, {:+}% chance", bonus),
+        }
+    }
+}
+
+/// Stat change granted by a piece of equipment while it is equipped
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Bonus {
+    pub stat: Stat,
+    pub amount: i32,
+}
+
+impl std::fmt::Display for Bonus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let amount = format!("{:+}", self.amount);
+        if self.amount < 0 {
+            write!(f, "{} {}", self.stat, amount.red())
+        } else {
+            write!(f, "{} {}", self.stat, amount.green())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Item {
+    pub name: String,
+    pub kind: ItemKind,
+    #[serde(default)]
+    pub rarity: Rarity,
+    pub description: String,
+    /// Worth in gold
+    pub value: u32,
+    #[serde(default)]
+    pub bonuses: Vec<Bonus>,
+    #[serde(default, rename = "use")]
+    pub effect: Option<ItemEffect>,
+}
+
+impl Item {
+    /// Short summary of what the item does
+    pub fn summary(&self) -> String {
+        match &self.effect {
+            Some(effect) => effect.to_string(),
+            None if self.kind == ItemKind::Valuable => format!("worth {} gold", self.value),
+            None => self.bonuses.iter()
+                .map(|b| b.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+        }
+    }
+
+    /// Gold paid by the tavern for the item. Valuables fetch their full
+    /// worth, anything else half of it.
+    pub fn sell_price(&self) -> u32 {
+        match self.kind {
+            ItemKind::Valuable => self.value,
+            _ => (self.value / 2).max(1),
+        }
+    }
+
+    /// Rolls one of the items of the given kinds, weighted by how common
+    /// they are
+    pub fn from_rng(rng: &mut RandomState, kinds: &[ItemKind]) -> Item {
+        roll(rng, |item| kinds.contains(&item.kind))
+            .expect("res/items.json has no item of the kinds")
+    }
+
+    /// Rolls a rarity for the floor, then one of the items of that rarity
+    pub fn from_treasure(rng: &mut RandomState, depth: u32) -> Item {
+        let rarity = *Rarity::ALL.choose_weighted(&mut rng.rng, |r| r.weight(depth)).unwrap();
+        roll(rng, |item| item.rarity == rarity && item.kind != ItemKind::Valuable)
+            .expect("res/items.json has no treasure of the rarity")
+    }
+}
+
+/// The item name in the color of its rarity
+impl std::fmt::Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.rarity.paint(&self.name).fmt(f)
+    }
+}
+
+/// Definition of an item as read from `res/items.json`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct ItemDef {
+    #[serde(flatten)]
+    item: Item,
+    /// Relative odds of the item being rolled
+    weight: u32,
+}
+
+fn catalogue() -> Vec<ItemDef> {
+    serde_json::from_str(include_str!("../../res/items.json"))
+        .expect("res/items.json is malformed")
+}
+
+/// Rolls one of the matching items, weighted by how common they are
+fn roll(rng: &mut RandomState, filter: impl Fn(&Item) -> bool) -> Option<Item> {
+    catalogue().into_iter()
+        .filter(|d| filter(&d.item))
+        .collect::<Vec<ItemDef>>()
+        .choose_weighted(&mut rng.rng, |d| d.weight)
+        .ok()
+        .map(|d| d.item.clone())
+}
+
+/// Items always on sale in the tavern: everything common and uncommon,
+/// save for valuables
+pub fn tavern_stock() -> Vec<Item> {
+    catalogue().into_iter()
+        .map(|d| d.item)
+        .filter(|i| i.kind != ItemKind::Valuable && matches!(i.rarity, Rarity::Common | Rarity::Uncommon))
+        .collect()
+}
+
+/// Looks the item up by name, ignoring case
+pub fn find_item(name: &str) -> Option<Item> {
+    catalogue().into_iter()
+        .map(|d| d.item)
+        .find(|i| i.name.eq_ignore_ascii_case(name))
+}
+
+/// Items carried by a character, along with the equipment they wear. At
+/// most one item of each equipment kind is equipped at a time.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Inventory {
+    items: Vec<Item>,
+    equipped: Vec<Item>,
+    #[serde(default)]
+    gold: u32,
+}
+
+impl Inventory {
+    /// Two healing potions and a piece of equipment rolled from the items
+    pub fn starting_kit(rng: &mut RandomState) -> Inventory {
+        let mut inventory = Inventory::default();
+        let potion = find_item("Healing Potion").expect("res/items.json has no Healing Potion");
+        inventory.add(potion.clone());
+        inventory.add(potion);
+        inventory.add(Item::from_rng(rng, &[ItemKind::Weapon, ItemKind::Armor, ItemKind::Trinket]));
+        inventory
+    }
+
+    /// The item worn in the slot of the kind, if any
+    pub fn slot(&self, kind: ItemKind) -> Option<&Item> {
+        self.equipped.iter().find(|i| i.kind == kind)
+    }
+
+    pub fn add(&mut self, item: Item) {
+        self.items.push(item);
+    }
+
+    pub fn gold(&self) -> u32 {
+        self.gold
+    }
+
+    /// Pays the amount, as long as there is enough gold
+    pub fn spend(&mut self, amount: u32) -> bool {
+        if self.gold < amount {
+            return false;
+        }
+        self.gold -= amount;
+        true
+    }
+
+    pub fn earn(&mut self, amount: u32) {
+        self.gold += amount;
+    }
+
+    /// Packs away everything in the loot
+    pub fn stash(&mut self, loot: Loot) {
+        self.items.extend(loot.items);
+        self.gold += loot.gold;
+    }
+
+    /// Takes a carried item out of the inventory by name, ignoring case
+    pub fn take(&mut self, name: &str) -> Option<Item> {
+        let index = self.items.iter().position(|i| i.name.eq_ignore_ascii_case(name))?;
+        Some(self.items.remove(index))
+    }
+
+    /// Puts the item in its slot, returning the item it replaces
+    pub fn equip(&mut self, item: Item) -> Option<Item> {
+        let replaced = self.equipped.iter()
+            .position(|i| i.kind == item.kind)
+            .map(|index| self.equipped.remove(index));
+        self.equipped.push(item);
+        replaced
+    }
+
+    /// Takes an equipped item out of its slot by name, ignoring case
+    pub fn unequip(&mut self, name: &str) -> Option<Item> {
+        let index = self.equipped.iter().position(|i| i.name.eq_ignore_ascii_case(name))?;
+        Some(self.equipped.remove(index))
+    }
+
+    /// Carried items grouped by name, with how many of each there are
+    pub fn stacks(&self) -> Vec<(&Item, usize)> {
+        let mut stacks: Vec<(&Item, usize)> = vec![];
+        for item in &self.items {
+            match stacks.iter_mut().find(|(i, _)| i.name == item.name) {
+                Some((_, count)) => *count += 1,
+                None => stacks.push((item, 1)),
+            }
+        }
+        stacks
+    }
+}
+
+impl std::fmt::Display for Inventory {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "   {}", "Equipment".bold())?;
+        for kind in [ItemKind::Weapon, ItemKind::Armor, ItemKind::Trinket] {
+            match self.slot(kind) {
+                Some(item) => writeln!(f, "   {:<8} {:<22} {}", kind.to_string(), item, item.summary())?,
+                None => writeln!(f, "   {:<8} {}", kind.to_string(), "-".black())?,
+            }
+        }
+
+        writeln!(f, "\n   {}", "Backpack".bold())?;
+        writeln!(f, "   {:>3} gold", self.gold.to_string().yellow())?;
+        for (item, count) in self.stacks() {
+            writeln!(f, "   {:>2}x {:<22} {} {}",
+                count, item, item.summary(), format!("({})", item.kind).black()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Items and gold found on a fallen monster or in a treasure chest
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Loot {
+    pub items: Vec<Item>,
+    pub gold: u32,
+}
+
+impl Loot {
+    /// Rolls the contents of a treasure chest on the floor at the given depth
+    pub fn from_treasure(rng: &mut RandomState, depth: u32) -> Loot {
+        let count = if rng.rng.gen_bool(0.3) { 2 } else { 1 };
+        Loot {
+            items: (0..count).map(|_| Item::from_treasure(rng, depth)).collect(),
+            gold: rng.rng.gen_range(5..=20) * depth,
+        }
+    }
+}
+
+impl std::fmt::Display for Loot {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut parts: Vec<String> = self.items.iter().map(|i| i.to_string()).collect();
+        if self.gold > 0 {
+            parts.push(format!("{} gold", self.gold.to_string().yellow()));
+        }
+
+        match parts.len() {
+            0 => write!(f, "nothing"),
+            1 => write!(f, "{}", parts[0]),
+            n => write!(f, "{} and {}", parts[..n - 1].join(", "), parts[n - 1]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [ItemKind; 3] = [ItemKind::Consumable, ItemKind::Weapon, ItemKind::Valuable];
+
+    fn roll(seed: u64, kinds: &[ItemKind]) -> Vec<String> {
+        let mut state = RandomState::seeded(seed);
+        (0..20).map(|_| Item::from_rng(&mut state, kinds).name).collect()
+    }
+
+    #[test]
+    fn items_are_rolled_from_the_seed() {
+        assert_eq!(roll(7, &KINDS), roll(7, &KINDS));
+        assert_ne!(roll(7, &KINDS), roll(8, &KINDS));
+    }
+
+    #[test]
+    fn items_are_rolled_from_the_given_kinds() {
+        let mut state = RandomState::seeded(7);
+        for _ in 0..50 {
+            assert_eq!(Item::from_rng(&mut state, &[ItemKind::Armor]).kind, ItemKind::Armor);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use std::cell::RefCell;
+
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use super::{common::*, error::ClrpgError, items::Loot};
+
+/// Gold the treasury of a new dungeon starts out with
+pub const STARTING_TREASURY: u32 = 100;
+/// Share of looted gold, in percent, tithed to the treasury
+pub const TITHE_PERCENT: u32 = 10;
+/// Gold paid out of the treasury for every victory of a run that makes it
+/// back out of the dungeon
+pub const BOUNTY_PER_VICTORY: u32 = 5;
+/// Fee paid out of the treasury for every adventurer hired
+pub const HIRING_FEE: u32 = 25;
+
+/// Where the gold of a transaction went in or out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Account {
+    /// The purse of an adventurer
+    Purse,
+    /// The dungeon-wide treasury
+    Treasury,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Reason {
+    /// Gold carried by a fallen monster
+    Loot,
+    /// Gold found in a treasure chest
+    Treasure,
+    /// Share of looted gold owed to the treasury
+    Tithe,
+    /// Reward paid for the victories of a run
+    Bounty,
+    Purchase,
+    Sale,
+    /// A night in a tavern room
+    Lodging,
+    /// Fee for hiring a new adventurer
+    Hiring,
+    /// Fee for the temple's rites reviving the fallen
+    Revival,
+}
+
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", format!("{:?}", self).to_uppercase())
+    }
+}
+
+/// Entry in `.dungeon/.ledger`, one per movement of gold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transaction {
+    pub at: chrono::NaiveDateTime,
+    pub account: Account,
+    /// Owner of the purse, or the adventurer the treasury dealt with
+    pub character: Option<String>,
+    /// Gold earned (positive) or spent (negative)
+    pub amount: i64,
+    pub reason: Reason,
+}
+
+impl std::fmt::Display for Transaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let amount = format!("{:+}", self.amount);
+        let amount = if self.amount < 0 { amount.red() } else { amount.green() };
+        let account = match self.account {
+            Account::Purse => "PURSE",
+            Account::Treasury => "TREASURY",
+        };
+        write!(f, "{}  {:<8} {:<20} {:>6}  {}",
+            self.at.format("%Y-%m-%d %H:%M"), account,
+            self.character.as_deref().unwrap_or("-"), amount, self.reason
+        )
+    }
+}
+
+thread_local! {
+    /// Transactions held back until the purse or treasury they moved gold
+    /// in or out of has been written
+    static PENDING: RefCell<Vec<Transaction>> = const { RefCell::new(vec![]) };
+}
+
+fn record(account: Account, character: &str, amount: i64, reason: Reason) {
+    let transaction = Transaction {
+        at: chrono::Local::now().naive_local(),
+        account,
+        character: Some(character.to_string()),
+        amount,
+        reason,
+    };
+    PENDING.with(|pending| pending.borrow_mut().push(transaction));
+}
+
+/// Appends the pending transactions that match to the ledger
+fn flush(matches: impl Fn(&Transaction) -> bool) -> Result<(), ClrpgError> {
+    let flushed: Vec<Transaction> = PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        let (flushed, kept) = std::mem::take(&mut *pending).into_iter().partition(|t| matches(t));
+        *pending = kept;
+        flushed
+    });
+    for transaction in flushed {
+        append_to_file(&transaction, LEDGER_FILE_NAME)?;
+    }
+    Ok(())
+}
+
+/// Records the pending transactions of the character's purse, once it has
+/// been written
+pub fn flush_purse(name: &str) -> Result<(), ClrpgError> {
+    flush(|t| t.account == Account::Purse && t.character.as_deref() == Some(name))
+}
+
+/// Reads every recorded transaction, skipping entries that cannot be parsed
+pub fn load_ledger() -> Vec<Transaction> {
+    // there is no ledger until the first gold changes hands
+    read_lines(LEDGER_FILE_NAME)
+}
+
+/// Points the transactions of the character at their new name
+pub fn rename_in_ledger(old: &str, new: &str) -> Result<(), ClrpgError> {
+    let mut ledger = load_ledger();
+    if !ledger.iter().any(|t| t.character.as_deref() == Some(old)) {
+        return Ok(());
+    }
+
+    for transaction in ledger.iter_mut().filter(|t| t.character.as_deref() == Some(old)) {
+        transaction.character = Some(new.to_string());
+    }
+    write_lines(&ledger, LEDGER_FILE_NAME)
+}
+
+/// Gold shared by every adventurer of the dungeon, kept in `.dungeon/.treasury`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Treasury {
+    gold: u32,
+}
+
+impl Treasury {
+    pub fn gold(&self) -> u32 {
+        self.gold
+    }
+
+    pub fn deposit(&mut self, amount: u32, character: &str, reason: Reason) {
+        self.gold += amount;
+        record(Account::Treasury, character, amount as i64, reason);
+    }
+
+    /// Pays the amount out, as long as the treasury holds enough gold
+    pub fn withdraw(&mut self, amount: u32, character: &str, reason: Reason) -> Result<(), ClrpgError> {
+        if self.gold < amount {
+            return Err(ClrpgError::NotEnoughGold { name: "The treasury".to_string(), cost: amount, gold: self.gold });
+        }
+        self.gold -= amount;
+        record(Account::Treasury, character, -(amount as i64), reason);
+        Ok(())
+    }
+}
+
+pub fn create_treasury() -> Result<(), ClrpgError> {
+    write_treasury(&Treasury { gold: STARTING_TREASURY })
+}
+
+/// Writes the treasury, then records its pending transactions
+pub fn write_treasury(treasury: &Treasury) -> Result<(), ClrpgError> {
+    write_to_dir(treasury, TREASURY_FILE_NAME)?;
+    flush(|t| t.account == Account::Treasury)
+}
+
+/// Reads the treasury. Dungeons from before the treasury existed start out
+/// with a fresh one.
+pub fn require_treasury() -> Result<Treasury, ClrpgError> {
+    if !check_dir(TREASURY_FILE_NAME)? {
+        return Ok(Treasury { gold: STARTING_TREASURY });
+    }
+    require_file(TREASURY_FILE_NAME)
+}
+
+/// Puts the gold into the character's purse. The transaction is recorded
+/// once the character is written.
+pub fn earn(character: &mut CharacterObj, amount: u32, reason: Reason) {
+    character.inventory_mut().earn(amount);
+    record(Account::Purse, character.get_name(), amount as i64, reason);
+}
+
+/// Takes the gold out of the character's purse, as long as there is enough.
+/// The transaction is recorded once the character is written.
+pub fn spend(character: &mut CharacterObj, amount: u32, reason: Reason) -> Result<(), ClrpgError> {
+    let gold = character.inventory().gold();
+    if !character.inventory_mut().spend(amount) {
+        return Err(ClrpgError::NotEnoughGold { name: character.get_name().to_string(), cost: amount, gold });
+    }
+    record(Account::Purse, character.get_name(), -(amount as i64), reason);
+    Ok(())
+}
+
+/// Packs the loot away, tithing a share of its gold to the treasury.
+/// Returns the gold tithed.
+pub fn collect(
+    character: &mut CharacterObj, mut loot: Loot, reason: Reason, treasury: &mut Treasury
+) -> u32 {
+    let tithe = loot.gold * TITHE_PERCENT / 100;
+    let kept = std::mem::take(&mut loot.gold) - tithe;
+
+    character.inventory_mut().stash(loot);
+    if kept > 0 {
+        earn(character, kept, reason);
+    }
+    if tithe > 0 {
+        treasury.deposit(tithe, character.get_name(), Reason::Tithe);
+    }
+    tithe
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::common::tests::test_character;
+
+    fn pending() -> Vec<(Account, i64)> {
+        PENDING.with(|pending| pending.borrow().iter().map(|t| (t.account, t.amount)).collect())
+    }
+
+    #[test]
+    fn purses_cannot_go_below_zero() {
+        let mut character = test_character("Ana", CharacterStats::new(100, 10, 10, 10, 10, 10));
+        earn(&mut character, 5, Reason::Loot);
+
+        let spent = spend(&mut character, 10, Reason::Purchase);
+        assert!(matches!(spent, Err(ClrpgError::NotEnoughGold { cost: 10, gold: 5, .. })));
+        assert_eq!(character.inventory().gold(), 5);
+        assert_eq!(pending(), [(Account::Purse, 5)]);
+    }
+
+    #[test]
+    fn the_treasury_cannot_pay_out_more_than_it_holds() {
+        let mut treasury = Treasury { gold: 10 };
+        let paid = treasury.withdraw(20, "Ana", Reason::Sale);
+        assert!(matches!(paid, Err(ClrpgError::NotEnoughGold { cost: 20, gold: 10, .. })));
+        assert_eq!(treasury.gold(), 10);
+        assert!(pending().is_empty());
+    }
+
+    #[test]
+    fn looted_gold_is_tithed_to_the_treasury() {
+        let mut character = test_character("Ana", CharacterStats::new(100, 10, 10, 10, 10, 10));
+        let mut treasury = Treasury { gold: 0 };
+        let tithe = collect(&mut character, Loot { items: vec![], gold: 50 }, Reason::Loot, &mut treasury);
+
+        assert_eq!(tithe, 50 * TITHE_PERCENT / 100);
+        assert_eq!(character.inventory().gold() + treasury.gold(), 50);
+        assert_eq!(pending(), [(Account::Purse, (50 - tithe) as i64), (Account::Treasury, tithe as i64)]);
+    }
+}
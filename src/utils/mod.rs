@@ -1,2 +1,9 @@
 pub mod print;
-pub mod common;
\ No newline at end of file
+pub mod common;
+pub mod error;
+pub mod combat;
+pub mod bestiary;
+pub mod classes;
+pub mod dungeon;
+pub mod items;
+pub mod ledger;
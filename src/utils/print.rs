@@ -1,53 +1,142 @@
 
-use clap::ArgEnum;
+use clap::{ArgEnum, Args};
 use colored::Colorize;
-use std::cell::RefCell;
+use serde::Serialize;
+use std::cell::Cell;
 use std::io::stdout;
 use std::time::Duration;
 use std::thread::sleep;
 use std::io::Write;
 
-thread_local! { pub static VERBOSE: RefCell<bool> = RefCell::new(true); }
+use crate::errln;
+
+thread_local! {
+    /// Logging level: 0 shows errors only (none in JSON mode), 1 adds info and
+    /// warnings, 2 adds debug traces
+    pub static VERBOSE: Cell<u8> = const { Cell::new(0) };
+    /// Whether narration is suppressed
+    pub static QUIET: Cell<bool> = const { Cell::new(false) };
+    /// Whether command output is printed as JSON
+    pub static JSON: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+pub fn set_verbosity(level: u8, quiet: bool) {
+    VERBOSE.with(|v| v.set(level));
+    QUIET.with(|q| q.set(quiet));
+}
+
+/// Switches command output to JSON. Colors are turned off so no escape
+/// codes end up inside the JSON strings.
+pub fn set_format(format: OutputFormat) {
+    JSON.with(|j| j.set(format == OutputFormat::Json));
+    if format == OutputFormat::Json {
+        colored::control::set_override(false);
+    }
+}
+
+pub fn is_json() -> bool {
+    JSON.with(|j| j.get())
+}
+
+pub fn print_json<T: Serialize + ?Sized>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(s) => println!("{}", s),
+        Err(err) => errln!("Unable to serialize output: {}", err),
+    }
+}
+
+/// Prints a one line outcome, wrapped in a `message` object in JSON mode
+pub fn print_message(s: &str) {
+    if is_json() {
+        print_json(&serde_json::json!({ "message": s }));
+    } else {
+        println!("{}", s);
+    }
+}
+
+pub fn verbosity() -> u8 {
+    VERBOSE.with(|v| v.get())
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.with(|q| q.get())
+}
 
 #[macro_export]
 macro_rules! errln {
     () => {
-        print!("\n")
+        if !$crate::utils::print::is_json() || $crate::utils::print::verbosity() >= 1 {
+            eprintln!()
+        }
     };
     ($($arg:tt)*) => {{
-        crate::utils::print::VERBOSE.with(|b| {
-            let inner = b.borrow_mut();
-            if *inner {
-                std::io::_print(std::format_args_nl!("{:>8} {}", "Error".red().bold(), std::format_args!($($arg)*)));
-            }
-        });
+        use colored::Colorize;
+        // in JSON mode stderr is left to the error object, unless logs were asked for
+        if !$crate::utils::print::is_json() || $crate::utils::print::verbosity() >= 1 {
+            eprintln!("{:>8} {}", "Error".red().bold(), format_args!($($arg)*));
+        }
     }};
 }
 
 #[macro_export]
 macro_rules! infoln {
     ($($arg:tt)*) => {{
-        crate::utils::print::VERBOSE.with(|b| {
-            let inner = b.borrow_mut();
-            if *inner {
-                std::io::_print(std::format_args_nl!("{:>8} {}", "Info".green().bold(), std::format_args!($($arg)*)));
-            }
-        });
+        use colored::Colorize;
+        if $crate::utils::print::verbosity() >= 1 {
+            eprintln!("{:>8} {}", "Info".green().bold(), format_args!($($arg)*));
+        }
     }};
 }
 
 #[macro_export]
 macro_rules! warnln {
     ($($arg:tt)*) => {{
-        crate::utils::print::VERBOSE.with(|b| {
-            let inner = b.borrow_mut();
-            if *inner {
-                std::io::_print(std::format_args_nl!("{:>8} {}", "Warn".yellow().bold(), std::format_args!($($arg)*)));
-            }
-        });
+        use colored::Colorize;
+        if $crate::utils::print::verbosity() >= 1 {
+            eprintln!("{:>8} {}", "Warn".yellow().bold(), format_args!($($arg)*));
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! debugln {
+    ($($arg:tt)*) => {{
+        use colored::Colorize;
+        if $crate::utils::print::verbosity() >= 2 {
+            eprintln!("{:>8} {}", "Debug".blue().bold(), format_args!($($arg)*));
+        }
     }};
 }
 
+/// Asks the player a question on stderr and reads the answer from stdin.
+/// Returns `None` once stdin is closed.
+pub fn prompt(question: &str) -> Option<String> {
+    eprint!("{:>8} {} ", "Choose".magenta().bold(), question);
+    let _ = std::io::stderr().flush();
+
+    let mut answer = String::new();
+    match std::io::stdin().read_line(&mut answer) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(answer.trim().to_string()),
+    }
+}
+
+/// Lists numbered options on stderr, ahead of the prompt that picks one.
+/// Nothing is listed under `--quiet` or in JSON mode.
+pub fn print_choices(choices: &[String]) {
+    if is_quiet() || is_json() {
+        return;
+    }
+    for (i, choice) in choices.iter().enumerate() {
+        eprintln!("{:>8} {}", format!("{})", i + 1), choice);
+    }
+}
 
 pub fn print_logo() {
     
@@ -62,7 +151,41 @@ pub enum NarrateSpeed {
     Norm
 }
 
+#[derive(Debug, Clone, Args)]
+pub struct NarrateOptions {
+    /// Narration speed; default norm
+    #[clap(short, long, arg_enum, value_name = "SPEED")]
+    narrate_speed: Option<NarrateSpeed>,
+
+    /// Narration wrapping length; default 75
+    #[clap(short, long, value_parser)]
+    wrapping: Option<usize>,
+}
+
+impl NarrateOptions {
+    pub fn narrate(&self, s: &str) {
+        let speed = match &self.narrate_speed {
+            Some(s) => s.clone(),
+            _ => NarrateSpeed::Norm
+        };
+
+        let wrap = match &self.wrapping {
+            Some(v) => *v,
+            _ => 75usize
+        };
+
+        narrate(s, speed, wrap);
+    }
+}
+
 pub fn narrate(s: &str, speed: NarrateSpeed, wrapping_len: usize) {
+    if is_json() {
+        print_json(&serde_json::json!({ "story": s }));
+        return;
+    }
+    if is_quiet() {
+        return;
+    }
 
     let sleep_time = match speed {
         NarrateSpeed::Slow => 80,